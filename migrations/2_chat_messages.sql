@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id VARCHAR(26) PRIMARY KEY,
+    room VARCHAR(128) NOT NULL,
+    username VARCHAR(128) NOT NULL,
+    message TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS chat_messages_room_id ON chat_messages (room, id);
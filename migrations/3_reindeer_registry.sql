@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS reindeer (
+    name VARCHAR(100) PRIMARY KEY,
+    strength BIGINT NOT NULL,
+    speed DOUBLE PRECISION NOT NULL,
+    height BIGINT NOT NULL,
+    antler_width BIGINT NOT NULL,
+    snow_magic_power BIGINT NOT NULL,
+    favorite_food VARCHAR(100) NOT NULL,
+    candies_eaten_yesterday BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS contests (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    roster JSONB NOT NULL
+);
@@ -0,0 +1,29 @@
+use sebatustra_christmas::{build_router, build_state, config::AppConfig, init_tracing, migrate};
+use sqlx::PgPool;
+use tracing::info;
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+
+    let config = AppConfig::load().unwrap();
+    init_tracing(config.log_format);
+
+    let database_url = std::env::var("DATABASE_URL")
+        .expect("DATABASE_URL must be set to run outside of Shuttle");
+    let pool = PgPool::connect(&database_url).await.unwrap();
+    migrate(&pool).await.unwrap();
+
+    let address = config.bind_address;
+    let state = build_state(pool, config).unwrap();
+
+    info!(%address, "listening");
+
+    axum::Server::bind(&address)
+        .serve(build_router(state).into_make_service())
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .unwrap();
+}
@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, net::SocketAddr, process, time::Duration};
+
+use sebatustra_christmas::{
+    build_router,
+    build_state,
+    config::AppConfig,
+    migrate,
+    validator::{load_cases, run_case}
+};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+const USAGE: &str = "usage: validate [--cases validation/cases.jsonl] [--base-url http://host:port] [--day N]";
+
+struct Args {
+    cases: String,
+    base_url: Option<String>,
+    day: Option<i32>
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        cases: "validation/cases.jsonl".to_string(),
+        base_url: None,
+        day: None
+    };
+
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        let value = match raw.next() {
+            Some(value) => value,
+            None => return Err(format!("{} needs a value", flag))
+        };
+
+        match flag.as_str() {
+            "--cases" => args.cases = value,
+            "--base-url" => args.base_url = Some(value),
+            "--day" => match value.parse() {
+                Ok(day) => args.day = Some(day),
+                Err(_) => return Err(format!("{:?} is not a day", value))
+            },
+            _ => return Err(format!("unknown flag {}", flag))
+        }
+    }
+
+    Ok(args)
+}
+
+// Without a base url the router is served on a local port in this process,
+// against DATABASE_URL when it is set.
+async fn serve_in_process() -> Result<String, String> {
+    let config = AppConfig::load()?;
+
+    let pool = match std::env::var("DATABASE_URL") {
+        Ok(url) => {
+            let pool = match PgPool::connect(&url).await {
+                Ok(pool) => pool,
+                Err(e) => return Err(format!("could not connect to {}: {}", url, e))
+            };
+            migrate(&pool).await?;
+            pool
+        },
+        Err(_) => {
+            println!("DATABASE_URL is not set, database cases will fail");
+            match PgPoolOptions::new()
+                .acquire_timeout(Duration::from_millis(100))
+                .connect_lazy("postgres://localhost:1/christmas") {
+                    Ok(pool) => pool,
+                    Err(e) => return Err(e.to_string())
+                }
+        }
+    };
+
+    let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .serve(build_router(build_state(pool, config)?).into_make_service());
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    Ok(format!("http://{}", address))
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(reason) => {
+            eprintln!("{}\n{}", reason, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let cases = match load_cases(&args.cases) {
+        Ok(cases) => cases,
+        Err(reason) => {
+            eprintln!("{}", reason);
+            process::exit(2);
+        }
+    };
+
+    let base_url = match args.base_url {
+        Some(base_url) => base_url,
+        None => match serve_in_process().await {
+            Ok(base_url) => base_url,
+            Err(reason) => {
+                eprintln!("{}", reason);
+                process::exit(2);
+            }
+        }
+    };
+
+    let client = reqwest::Client::new();
+
+    // day -> (passed, failed case reports)
+    let mut days: BTreeMap<i32, (usize, Vec<String>)> = BTreeMap::new();
+
+    for case in cases.iter().filter(|case| args.day.is_none_or(|day| case.day == day)) {
+        let failures = run_case(&client, &base_url, case).await;
+        let day = days.entry(case.day).or_insert((0, Vec::new()));
+
+        if failures.is_empty() {
+            day.0 += 1;
+        } else {
+            let details = failures.join("\n").replace('\n', "\n      ");
+            day.1.push(format!("  FAIL {} {} {}\n      {}", case.name, case.method, case.path, details));
+        }
+    }
+
+    let mut passed = 0;
+    let mut total = 0;
+    for (day, (day_passed, failures)) in &days {
+        let day_total = day_passed + failures.len();
+        println!("day {:>3}: {}/{} passed", day, day_passed, day_total);
+        for failure in failures {
+            println!("{}", failure);
+        }
+
+        passed += day_passed;
+        total += day_total;
+    }
+    println!("total: {}/{} passed against {}", passed, total, base_url);
+
+    if passed != total {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{env, fs, net::SocketAddr, path::{Path, PathBuf}, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+// Every challenge day with routes, "-1" being the warm-up error route.
+pub const CHALLENGE_DAYS: [i32; 17] = [-1, 1, 4, 5, 6, 7, 8, 11, 12, 13, 14, 15, 18, 19, 20, 21, 22];
+
+/// How log lines are formatted, by both the Shuttle and standalone binaries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {:?}", format))
+        }
+    }
+}
+
+/// Settings read at startup from an optional TOML file (`CONFIG_PATH`,
+/// `config.toml` by default) and then overridden by environment variables.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub bind_address: SocketAddr,
+    pub asset_root: PathBuf,
+    pub pokeapi_url: String,
+    pub body_limit: usize,
+    pub upstream_timeout_secs: u64,
+    pub policies_path: String,
+    pub breached_path: String,
+    pub chat_replay_window: usize,
+    pub disabled_days: Vec<i32>,
+    pub log_format: LogFormat
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            asset_root: PathBuf::from("assets"),
+            pokeapi_url: "https://pokeapi.co/api/v2".to_string(),
+            body_limit: 2 * 1024 * 1024,
+            upstream_timeout_secs: 10,
+            policies_path: "policies.toml".to_string(),
+            breached_path: "breached_passwords.txt".to_string(),
+            chat_replay_window: 20,
+            disabled_days: Vec::new(),
+            log_format: LogFormat::Text
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn load() -> Result<Self, String> {
+        let mut config = match env::var("CONFIG_PATH") {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new("config.toml").exists() => Self::from_file("config.toml")?,
+            Err(_) => AppConfig::default()
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("could not read config {}: {}", path, e))
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => Ok(config),
+            Err(e) => Err(format!("invalid config {}: {}", path, e))
+        }
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        if let Some(bind_address) = env_value("BIND_ADDRESS")? {
+            self.bind_address = bind_address;
+        }
+        if let Some(asset_root) = env_value::<String>("ASSET_ROOT")? {
+            self.asset_root = PathBuf::from(asset_root);
+        }
+        if let Some(pokeapi_url) = env_value("POKEAPI_URL")? {
+            self.pokeapi_url = pokeapi_url;
+        }
+        if let Some(body_limit) = env_value("BODY_LIMIT")? {
+            self.body_limit = body_limit;
+        }
+        if let Some(timeout) = env_value("UPSTREAM_TIMEOUT_SECS")? {
+            self.upstream_timeout_secs = timeout;
+        }
+        if let Some(policies_path) = env_value("PASSWORD_POLICIES_PATH")? {
+            self.policies_path = policies_path;
+        }
+        if let Some(breached_path) = env_value("BREACHED_PASSWORDS_PATH")? {
+            self.breached_path = breached_path;
+        }
+        if let Some(window) = env_value("CHAT_REPLAY_WINDOW")? {
+            self.chat_replay_window = window;
+        }
+        if let Some(log_format) = env_value("LOG_FORMAT")? {
+            self.log_format = log_format;
+        }
+        if let Some(days) = env_value::<String>("DISABLED_DAYS")? {
+            self.disabled_days = Vec::new();
+            for day in days.split(',').map(str::trim).filter(|day| !day.is_empty()) {
+                match day.parse::<i32>() {
+                    Ok(day) => self.disabled_days.push(day),
+                    Err(_) => return Err(format!("DISABLED_DAYS: {:?} is not a day", day))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.asset_root.is_dir() {
+            return Err(format!("asset root {} is not a directory", self.asset_root.display()));
+        }
+
+        if !self.pokeapi_url.starts_with("http://") && !self.pokeapi_url.starts_with("https://") {
+            return Err(format!("pokeapi url {:?} must start with http:// or https://", self.pokeapi_url));
+        }
+
+        if self.body_limit == 0 {
+            return Err("body limit must be greater than 0".to_string());
+        }
+
+        if self.upstream_timeout_secs == 0 {
+            return Err("upstream timeout must be greater than 0".to_string());
+        }
+
+        if let Some(day) = self.disabled_days.iter().find(|day| !CHALLENGE_DAYS.contains(day)) {
+            return Err(format!("day {} has no routes to disable", day));
+        }
+
+        Ok(())
+    }
+
+    pub fn day_enabled(&self, day: i32) -> bool {
+        !self.disabled_days.contains(&day)
+    }
+
+    pub fn upstream_timeout(&self) -> Duration {
+        Duration::from_secs(self.upstream_timeout_secs)
+    }
+}
+
+fn env_value<T: FromStr>(name: &str) -> Result<Option<T>, String> {
+    match env::var(name) {
+        Ok(value) => match value.parse::<T>() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(format!("{}: {:?} is not a valid value", name, value))
+        },
+        Err(_) => Ok(None)
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// Side, in degrees, of the grid cells used to narrow down which polygons
+// can contain a point before running the point-in-polygon test.
+const GRID_SIZE: f64 = 10.0;
+
+#[derive(Deserialize)]
+struct Dataset {
+    countries: Vec<CountryRecord>
+}
+
+#[derive(Deserialize)]
+struct CountryRecord {
+    name: String,
+    polygons: Vec<Vec<[f64; 2]>>,
+    #[serde(default)]
+    holes: Vec<Vec<[f64; 2]>>
+}
+
+struct Polygon {
+    country: usize,
+    ring: Vec<[f64; 2]>,
+    min: [f64; 2],
+    max: [f64; 2],
+    hole: bool
+}
+
+pub struct CountryIndex {
+    names: Vec<String>,
+    areas: Vec<f64>,
+    polygons: Vec<Polygon>,
+    grid: HashMap<(i32, i32), Vec<usize>>
+}
+
+impl CountryIndex {
+    /// The dataset compiled into the binary, see `data/countries.json`.
+    pub fn bundled() -> Result<Self, String> {
+        Self::from_json(include_str!("../data/countries.json"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let dataset: Dataset = match serde_json::from_str(json) {
+            Ok(dataset) => dataset,
+            Err(e) => return Err(format!("invalid country dataset: {}", e))
+        };
+
+        let mut index = CountryIndex {
+            names: Vec::new(),
+            areas: Vec::new(),
+            polygons: Vec::new(),
+            grid: HashMap::new()
+        };
+
+        for record in dataset.countries {
+            let country = index.names.len();
+            let mut area = 0.0;
+
+            let rings = record.polygons.into_iter().map(|ring| (ring, false))
+                .chain(record.holes.into_iter().map(|ring| (ring, true)));
+            for (ring, hole) in rings {
+                if ring.len() < 3 {
+                    return Err(format!("{} has a polygon with fewer than 3 points", record.name));
+                }
+
+                let mut min = [f64::MAX, f64::MAX];
+                let mut max = [f64::MIN, f64::MIN];
+                for point in &ring {
+                    min = [min[0].min(point[0]), min[1].min(point[1])];
+                    max = [max[0].max(point[0]), max[1].max(point[1])];
+                }
+
+                let polygon_id = index.polygons.len();
+                let (min_x, min_y) = grid_cell(min[0], min[1]);
+                let (max_x, max_y) = grid_cell(max[0], max[1]);
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        index.grid.entry((x, y)).or_default().push(polygon_id);
+                    }
+                }
+
+                if hole {
+                    area -= ring_area(&ring);
+                } else {
+                    area += ring_area(&ring);
+                }
+
+                index.polygons.push(Polygon {
+                    country,
+                    ring,
+                    min,
+                    max,
+                    hole
+                });
+            }
+
+            index.names.push(record.name);
+            index.areas.push(area);
+        }
+
+        Ok(index)
+    }
+
+    pub fn lookup(&self, lat: f64, lng: f64) -> Option<&str> {
+        let candidates = self.grid.get(&grid_cell(lng, lat))?;
+
+        // a country holds the point when more of its outlines than its holes
+        // contain it, which keeps exclaves nested inside enclaves right
+        let mut windings: HashMap<usize, i32> = HashMap::new();
+        for polygon in candidates.iter().map(|&polygon_id| &self.polygons[polygon_id]) {
+            let inside = lng >= polygon.min[0] && lng <= polygon.max[0]
+                && lat >= polygon.min[1] && lat <= polygon.max[1]
+                && contains(&polygon.ring, lng, lat);
+            if inside {
+                *windings.entry(polygon.country).or_default() += if polygon.hole { -1 } else { 1 };
+            }
+        }
+
+        // dependencies such as Greenland also sit inside their sovereign's
+        // outline, so the smallest country containing the point wins
+        windings.into_iter()
+            .filter(|&(_, winding)| winding > 0)
+            .map(|(country, _)| country)
+            .min_by(|&a, &b| self.areas[a].total_cmp(&self.areas[b]))
+            .map(|country| self.names[country].as_str())
+    }
+}
+
+fn grid_cell(lng: f64, lat: f64) -> (i32, i32) {
+    ((lng / GRID_SIZE).floor() as i32, (lat / GRID_SIZE).floor() as i32)
+}
+
+fn contains(ring: &[[f64; 2]], lng: f64, lat: f64) -> bool {
+    let mut inside = false;
+    let mut previous = ring[ring.len() - 1];
+
+    for &current in ring {
+        let crosses = (current[1] > lat) != (previous[1] > lat);
+        if crosses {
+            let intersection = current[0]
+                + (lat - current[1]) * (previous[0] - current[0]) / (previous[1] - current[1]);
+            if lng < intersection {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+
+    inside
+}
+
+fn ring_area(ring: &[[f64; 2]]) -> f64 {
+    let mut area = 0.0;
+    let mut previous = ring[ring.len() - 1];
+
+    for &current in ring {
+        area += previous[0] * current[1] - current[0] * previous[1];
+        previous = current;
+    }
+
+    (area / 2.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s2::cell_center;
+
+    #[test]
+    fn finds_capitals() {
+        let index = CountryIndex::bundled().unwrap();
+
+        let capitals = [
+            (48.86, 2.35, "France"),
+            (40.42, -3.70, "Spain"),
+            (38.72, -9.14, "Portugal"),
+            (52.52, 13.40, "Germany"),
+            (51.51, -0.13, "United Kingdom"),
+            (55.75, 37.62, "Russia"),
+            (39.90, 116.40, "China"),
+            (28.61, 77.21, "India"),
+            (35.68, 139.69, "Japan"),
+            (-18.88, 47.51, "Madagascar"),
+            (-35.28, 149.13, "Australia"),
+            (-15.79, -47.88, "Brazil"),
+            (-33.45, -70.67, "Chile"),
+            (38.90, -77.04, "United States"),
+            (51.05, -114.07, "Canada"),
+            (19.43, -99.13, "Mexico"),
+            (30.04, 31.24, "Egypt")
+        ];
+
+        for (lat, lng, country) in capitals {
+            assert_eq!(index.lookup(lat, lng), Some(country), "{}, {}", lat, lng);
+        }
+    }
+
+    #[test]
+    fn prefers_the_smallest_enclosing_polygon() {
+        let index = CountryIndex::bundled().unwrap();
+
+        assert_eq!(index.lookup(4.89, 114.94), Some("Brunei"));
+        assert_eq!(index.lookup(-29.6, 28.2), Some("Lesotho"));
+        assert_eq!(index.lookup(-26.2, 28.05), Some("South Africa"));
+    }
+
+    #[test]
+    fn covers_countries_beyond_the_capitals() {
+        let index = CountryIndex::bundled().unwrap();
+
+        let cities = [
+            (-12.05, -77.04, "Peru"),
+            (-1.29, 36.82, "Kenya"),
+            (59.91, 10.75, "Norway"),
+            (47.89, 106.91, "Mongolia"),
+            (6.52, 3.38, "Nigeria"),
+            (64.15, -21.94, "Iceland"),
+            (-41.29, 174.78, "New Zealand"),
+            (64.18, -51.72, "Greenland"),
+            (51.17, 71.45, "Kazakhstan"),
+            (-6.20, 106.85, "Indonesia"),
+            (43.94, 12.45, "San Marino"),
+            (41.9029, 12.4534, "Vatican City")
+        ];
+
+        for (lat, lng, country) in cities {
+            assert_eq!(index.lookup(lat, lng), Some(country), "{}, {}", lat, lng);
+        }
+    }
+
+    #[test]
+    fn separates_neighbours_at_the_border() {
+        let index = CountryIndex::bundled().unwrap();
+
+        let pairs = [
+            ((48.58, 7.74, "France"), (48.57, 7.82, "Germany")),
+            ((43.36, -1.77, "France"), (43.33, -1.80, "Spain")),
+            ((42.33, -83.05, "United States"), (42.30, -83.02, "Canada")),
+            ((31.77, -106.48, "United States"), (31.72, -106.45, "Mexico")),
+            ((46.20, 6.14, "Switzerland"), (46.19, 6.23, "France"))
+        ];
+
+        for ((lat_a, lng_a, a), (lat_b, lng_b, b)) in pairs {
+            assert_eq!(index.lookup(lat_a, lng_a), Some(a), "{}, {}", lat_a, lng_a);
+            assert_eq!(index.lookup(lat_b, lng_b), Some(b), "{}, {}", lat_b, lng_b);
+        }
+    }
+
+    #[test]
+    fn excludes_holes_cut_for_enclaves() {
+        let index = CountryIndex::bundled().unwrap();
+
+        // Büsingen sits inside Switzerland, Llívia inside France
+        assert_eq!(index.lookup(47.697, 8.69), Some("Germany"));
+        assert_eq!(index.lookup(42.464, 1.975), Some("Spain"));
+
+        // Madha is Omani land inside the Emirates, with Nahwa inside it again
+        assert_eq!(index.lookup(25.28, 56.32), Some("Oman"));
+        assert_eq!(index.lookup(25.273, 56.282), Some("United Arab Emirates"));
+    }
+
+    #[test]
+    fn returns_none_at_sea() {
+        let index = CountryIndex::bundled().unwrap();
+
+        assert_eq!(index.lookup(30.0, -45.0), None);
+        assert_eq!(index.lookup(-40.0, 80.0), None);
+    }
+
+    #[test]
+    fn resolves_s2_cells() {
+        let index = CountryIndex::bundled().unwrap();
+        let cell_id = u64::from_str_radix("0010000111110000011111100000111010111100000100111101111011000101", 2).unwrap();
+
+        let (lat, lng) = cell_center(cell_id);
+
+        assert_eq!(index.lookup(lat, lng), Some("Madagascar"));
+    }
+
+    #[test]
+    fn honours_holes_in_custom_datasets() {
+        let json = r#"{"countries": [
+            {"name": "Ring", "polygons": [[[0, 0], [4, 0], [4, 4], [0, 4]], [[1.5, 1.5], [2.5, 1.5], [2.5, 2.5], [1.5, 2.5]]],
+             "holes": [[[1, 1], [3, 1], [3, 3], [1, 3]]]}
+        ]}"#;
+        let index = CountryIndex::from_json(json).unwrap();
+
+        assert_eq!(index.lookup(0.5, 0.5), Some("Ring"));
+        assert_eq!(index.lookup(1.2, 1.2), None);
+        assert_eq!(index.lookup(2.0, 2.0), Some("Ring"));
+    }
+
+    #[test]
+    fn rejects_degenerate_polygons() {
+        let json = r#"{"countries": [{"name": "Nowhere", "polygons": [[[0, 0], [1, 1]]]}]}"#;
+
+        assert!(CountryIndex::from_json(json).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::io::Cursor;
+use std::{collections::{BTreeMap, HashMap, HashSet}, io::Cursor, sync::atomic::Ordering, time::Instant};
 use image::io::Reader as ImageReader;
 use regex::Regex;
 use sqlx::types::JsonValue;
@@ -10,62 +10,138 @@ use axum::{
     extract::{
         Path, 
         Query,
+        BodyStream,
         Multipart, 
         State,
+        OriginalUri,
         ws::{WebSocketUpgrade, WebSocket, Message},
     }, 
     Json, 
-    http::HeaderMap, response::{IntoResponse, Response},
+    body::Bytes,
+    http::{HeaderMap, HeaderValue, header::LINK}, response::{IntoResponse, Response},
     http::StatusCode,
 };
 use serde_json::{json, Value};
-use tokio::fs;
+use futures_util::{SinkExt, StreamExt};
+use tracing::{debug, error, warn};
+use tokio::{fs, sync::broadcast::error::RecvError};
 use uuid::Uuid;
 
 use crate::{
     types::{
         ApiResponse, 
-        Pagination
+        Pagination,
+        PageMeta,
+        ElfQuery,
+        StatsQuery,
+        AnalyticsMode,
+        AnalyticsQuery,
+        ResponseFormat,
+        StrengthQuery,
+        BitsQuery,
+        ChatQuery,
+        HistoryQuery
     }, 
     structs::{
         Reindeer, 
         ReindeerContest, 
-        ContestResult, UlidCalc, Order, RenderContent, Password, Region, RegionTotal,
+        StrengthSummary,
+        ContestResult, ContestRanks, ContestRow, ReindeerRow, AttributeStats, CONTEST_ATTRIBUTES, BitsBreakdown, UlidCalc, Order, RenderContent, Password, PasswordBatch, StrengthReport, PingPongGame, IncomingChat, ChatMessage, StoredChatMessage, StarMap, Region, RegionTotal, PatternRequest, TextStats, MAX_NGRAM, MAX_PASSWORD_LENGTH,
     }, 
-    utils::{extract_recipe, is_lsb_1}, state::{IdStore, PacketId, PgState},
+    health,
+    s2::{cell_center, format_dms, is_valid_cell},
+    utils::{extract_recipe, is_lsb_1, summarize_tar, find_cookie, find_patterns, pattern_matcher, collect_text_stats, xor_lines}, state::{PgState, ConfigStore, MetricsStore, PokeApiState, PolicyStore, BreachStore, ChatState, CountryStore},
 };
 
 pub async fn fake_error() -> ApiResponse {
     ApiResponse::ServerError
 }
 
-pub async fn cube_bits(
-    Path(nums): Path<String>
+pub async fn liveness() -> ApiResponse {
+    ApiResponse::JsonValue(json!({"status": "ok"}))
+}
+
+pub async fn readiness(
+    State(config): State<ConfigStore>,
+    State(pg): State<PgState>
+) -> ApiResponse {
+    match health::readiness(&pg.pool, &config.config.asset_root).await {
+        (true, report) => ApiResponse::JsonValue(report),
+        (false, report) => {
+            warn!(report = %report, "readiness check failed");
+            ApiResponse::Unavailable(report)
+        }
+    }
+}
+
+pub async fn export_metrics(
+    State(store): State<MetricsStore>,
+    State(pg): State<PgState>
+) -> ApiResponse {
+    match store.metrics.render(&pg.pool) {
+        Ok(text) => ApiResponse::Metrics(text),
+        Err(e) => {
+            error!(error = %e, "could not render metrics");
+            ApiResponse::ServerError
+        }
+    }
+}
+
+const MAX_PACKETS: usize = 20;
+const MAX_POWER: u32 = 64;
+
+pub async fn bits_expression(
+    Path(nums): Path<String>,
+    Query(query): Query<BitsQuery>
 ) -> ApiResponse {
     let strings: Vec<&str> = nums.split("/").collect();
 
-    let mut xor_accumulator: i64 = 0;
+    if strings.len() > MAX_PACKETS {
+        return ApiResponse::RequestErrorAndJson(json!({
+            "error": format!("at most {} numbers are allowed, got {}", MAX_PACKETS, strings.len())
+        }));
+    }
+
+    let mut numbers: Vec<i64> = Vec::with_capacity(strings.len());
 
-    for string in strings {
+    for (position, string) in strings.iter().enumerate() {
         match string.parse::<i64>() {
-            Ok(number) => xor_accumulator = xor_accumulator ^ number,
-            Err(_e) => return ApiResponse::ServerError
+            Ok(number) => numbers.push(number),
+            Err(_) => return ApiResponse::RequestErrorAndJson(json!({
+                "error": format!("segment {}: {:?} is not an integer", position + 1, string)
+            }))
         }
     }
 
-    ApiResponse::Integer(xor_accumulator.pow(3))
+    let power = query.pow.unwrap_or(3);
+    if power > MAX_POWER {
+        return ApiResponse::RequestErrorAndJson(json!({
+            "error": format!("pow must be at most {}", MAX_POWER)
+        }));
+    }
+
+    let breakdown = BitsBreakdown::evaluate(numbers, query.op.unwrap_or_default(), power);
+
+    if query.explain.unwrap_or(false) {
+        ApiResponse::JsonValue(json!(breakdown))
+    } else {
+        ApiResponse::String(breakdown.result)
+    }
 }
 
 pub async fn reindeer_strength(
+    Query(query): Query<StrengthQuery>,
     Json(reindeers): Json<Vec<Reindeer>>
 ) -> ApiResponse {
-    let mut sum: u64 = 0;
+    let summary = match StrengthSummary::compute(&reindeers, query.mode.unwrap_or_default()) {
+        Ok(summary) => summary,
+        Err(reason) => return ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    };
 
-    for reindeer in reindeers {
-        sum += reindeer.strength;
+    match query.format {
+        Some(ResponseFormat::Json) => ApiResponse::JsonValue(json!(summary)),
+        _ => ApiResponse::Unsigned(summary.total)
     }
-
-    ApiResponse::Unsigned(sum)
 }
 
 pub async fn reindeer_contest(
@@ -79,41 +155,504 @@ pub async fn reindeer_contest(
     }
 }
 
+pub async fn contest_analytics(
+    Query(query): Query<AnalyticsQuery>,
+    Json(roster): Json<Vec<Value>>
+) -> ApiResponse {
+    if query.mode == Some(AnalyticsMode::Compat) {
+        let contest_data: Vec<ReindeerContest> = match serde_json::from_value(Value::Array(roster)) {
+            Ok(contest_data) => contest_data,
+            Err(e) => return ApiResponse::RequestErrorAndJson(json!({"error": e.to_string()}))
+        };
+
+        return match ContestResult::get_result(contest_data) {
+            Some(contest_result) => ApiResponse::JsonValue(json!(contest_result)),
+            None => ApiResponse::RequestErrorAndJson(json!({"error": "no reindeer to compare"}))
+        };
+    }
+
+    let names: Vec<String> = roster.iter().enumerate().map(|(index, reindeer)| {
+        match reindeer.get("name").and_then(Value::as_str) {
+            Some(name) => name.to_string(),
+            None => format!("reindeer #{}", index)
+        }
+    }).collect();
+
+    let mut attributes = serde_json::Map::new();
+
+    for (attribute, key) in CONTEST_ATTRIBUTES {
+        let values: Vec<(String, Option<f64>)> = roster.iter()
+            .zip(names.iter())
+            .map(|(reindeer, name)| (name.clone(), reindeer.get(key).and_then(Value::as_f64)))
+            .collect();
+
+        attributes.insert(attribute.to_string(), json!(AttributeStats::compute(&values)));
+    }
+
+    ApiResponse::JsonValue(json!({
+        "count": roster.len(),
+        "attributes": attributes
+    }))
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|database_error| database_error.code())
+        .map(|code| code == "23505")
+        .unwrap_or(false)
+}
+
+async fn upsert_reindeer(
+    state: &PgState,
+    reindeer: ReindeerContest,
+    statement: &str
+) -> Result<u64, ApiResponse> {
+    let row = match ReindeerRow::try_from(reindeer) {
+        Ok(row) => row,
+        Err(reason) => return Err(ApiResponse::RequestErrorAndJson(json!({"error": reason})))
+    };
+
+    match sqlx::query(statement)
+        .bind(&row.name)
+        .bind(row.strength)
+        .bind(row.speed)
+        .bind(row.height)
+        .bind(row.antler_width)
+        .bind(row.snow_magic_power)
+        .bind(&row.favorite_food)
+        .bind(row.candies_eaten_yesterday)
+        .execute(&state.pool)
+        .await {
+            Ok(result) => Ok(result.rows_affected()),
+            Err(e) if is_unique_violation(&e) => Err(ApiResponse::RequestErrorAndJson(json!({
+                "error": format!("{} is already registered", row.name)
+            }))),
+            Err(e) => {
+                error!(error = %e, "could not save reindeer");
+                Err(ApiResponse::ServerError)
+            }
+        }
+}
+
+pub async fn register_reindeer(
+    State(state): State<PgState>,
+    Json(reindeer): Json<ReindeerContest>
+) -> ApiResponse {
+    match upsert_reindeer(&state, reindeer, "
+        INSERT INTO reindeer
+            (name, strength, speed, height, antler_width, snow_magic_power, favorite_food, candies_eaten_yesterday)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+    ").await {
+        Ok(_) => ApiResponse::Ok,
+        Err(response) => response
+    }
+}
+
+pub async fn update_reindeer(
+    Path(name): Path<String>,
+    State(state): State<PgState>,
+    Json(mut reindeer): Json<ReindeerContest>
+) -> ApiResponse {
+    reindeer.name = name;
+
+    match upsert_reindeer(&state, reindeer, "
+        UPDATE reindeer
+        SET strength = $2, speed = $3, height = $4, antler_width = $5,
+            snow_magic_power = $6, favorite_food = $7, candies_eaten_yesterday = $8
+        WHERE name = $1;
+    ").await {
+        Ok(0) => ApiResponse::NotFound,
+        Ok(_) => ApiResponse::Ok,
+        Err(response) => response
+    }
+}
+
+async fn fetch_registry(state: &PgState) -> Result<Vec<ReindeerContest>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ReindeerRow>("SELECT * FROM reindeer ORDER BY name ASC;")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(rows.into_iter().map(ReindeerContest::from).collect())
+}
+
+pub async fn list_reindeer(
+    State(state): State<PgState>
+) -> ApiResponse {
+    match fetch_registry(&state).await {
+        Ok(reindeers) => ApiResponse::JsonValue(json!(reindeers)),
+        Err(e) => {
+            error!(error = %e, "could not list reindeer");
+            ApiResponse::ServerError
+        }
+    }
+}
+
+pub async fn get_reindeer(
+    Path(name): Path<String>,
+    State(state): State<PgState>
+) -> ApiResponse {
+    match sqlx::query_as::<_, ReindeerRow>("SELECT * FROM reindeer WHERE name = $1;")
+        .bind(name)
+        .fetch_optional(&state.pool)
+        .await {
+            Ok(Some(row)) => ApiResponse::JsonValue(json!(ReindeerContest::from(row))),
+            Ok(None) => ApiResponse::NotFound,
+            Err(e) => {
+                error!(error = %e, "could not load reindeer");
+                ApiResponse::ServerError
+            }
+        }
+}
+
+pub async fn delete_reindeer(
+    Path(name): Path<String>,
+    State(state): State<PgState>
+) -> ApiResponse {
+    match sqlx::query("DELETE FROM reindeer WHERE name = $1;")
+        .bind(name)
+        .execute(&state.pool)
+        .await {
+            Ok(result) if result.rows_affected() == 0 => ApiResponse::NotFound,
+            Ok(_) => ApiResponse::Ok,
+            Err(e) => {
+                error!(error = %e, "could not delete reindeer");
+                ApiResponse::ServerError
+            }
+        }
+}
+
+pub async fn create_contest(
+    State(state): State<PgState>,
+    Json(roster): Json<Vec<ReindeerContest>>
+) -> ApiResponse {
+    // an empty roster runs the contest with everyone in the registry
+    let roster = if roster.is_empty() {
+        match fetch_registry(&state).await {
+            Ok(registry) => registry,
+            Err(e) => {
+                error!(error = %e, "could not load the registry for a contest");
+                return ApiResponse::ServerError
+            }
+        }
+    } else {
+        roster
+    };
+
+    let result = match ContestResult::get_result(roster.clone()) {
+        Some(result) => result,
+        None => return ApiResponse::RequestErrorAndJson(json!({"error": "no reindeer to compare"}))
+    };
+
+    match sqlx::query_scalar::<_, i32>("INSERT INTO contests (roster) VALUES ($1) RETURNING id;")
+        .bind(sqlx::types::Json(&roster))
+        .fetch_one(&state.pool)
+        .await {
+            Ok(id) => ApiResponse::JsonValue(json!({"id": id, "result": result})),
+            Err(e) => {
+                error!(error = %e, "could not save contest");
+                ApiResponse::ServerError
+            }
+        }
+}
+
+async fn fetch_contests(state: &PgState) -> Result<Vec<ContestRow>, sqlx::Error> {
+    sqlx::query_as::<_, ContestRow>("SELECT id, created_at, roster FROM contests ORDER BY id ASC;")
+        .fetch_all(&state.pool)
+        .await
+}
+
+pub async fn list_contests(
+    State(state): State<PgState>
+) -> ApiResponse {
+    match fetch_contests(&state).await {
+        Ok(contests) => {
+            let summaries: Vec<Value> = contests.iter().map(|contest| json!({
+                "id": contest.id,
+                "created_at": contest.created_at,
+                "reindeer": contest.roster.len()
+            })).collect();
+
+            ApiResponse::JsonValue(json!(summaries))
+        },
+        Err(e) => {
+            error!(error = %e, "could not list contests");
+            ApiResponse::ServerError
+        }
+    }
+}
+
+pub async fn get_contest(
+    Path(id): Path<i32>,
+    State(state): State<PgState>
+) -> ApiResponse {
+    let contest = match sqlx::query_as::<_, ContestRow>("SELECT id, created_at, roster FROM contests WHERE id = $1;")
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await {
+            Ok(Some(contest)) => contest,
+            Ok(None) => return ApiResponse::NotFound,
+            Err(e) => {
+                error!(error = %e, "could not load contest");
+                return ApiResponse::ServerError
+            }
+        };
+
+    let roster = contest.roster.0;
+
+    ApiResponse::JsonValue(json!({
+        "id": contest.id,
+        "created_at": contest.created_at,
+        "result": ContestResult::get_result(roster.clone()),
+        "rankings": ContestResult::rankings(&roster)
+            .into_iter()
+            .collect::<HashMap<String, ContestRanks>>(),
+        "roster": roster
+    }))
+}
+
+pub async fn reindeer_history(
+    Path(name): Path<String>,
+    State(state): State<PgState>
+) -> ApiResponse {
+    let contests = match fetch_contests(&state).await {
+        Ok(contests) => contests,
+        Err(e) => {
+            error!(error = %e, "could not load contests for history");
+            return ApiResponse::ServerError
+        }
+    };
+
+    let mut history: Vec<Value> = Vec::new();
+    let mut previous: Option<ContestRanks> = None;
+
+    for contest in contests {
+        let ranks = ContestResult::rankings(&contest.roster)
+            .into_iter()
+            .find(|(reindeer, _)| *reindeer == name)
+            .map(|(_, ranks)| ranks);
+
+        let ranks = match ranks {
+            Some(ranks) => ranks,
+            None => continue
+        };
+
+        // positive numbers mean the reindeer climbed since its last contest
+        let change = previous.map(|before| json!({
+            "fastest": before.fastest as i64 - ranks.fastest as i64,
+            "tallest": before.tallest as i64 - ranks.tallest as i64,
+            "magician": before.magician as i64 - ranks.magician as i64,
+            "consumer": before.consumer as i64 - ranks.consumer as i64
+        }));
+
+        history.push(json!({
+            "contest": contest.id,
+            "created_at": contest.created_at,
+            "ranks": ranks,
+            "change": change
+        }));
+        previous = Some(ranks);
+    }
+
+    if history.is_empty() {
+        return ApiResponse::NotFound
+    }
+
+    ApiResponse::JsonValue(json!({"name": name, "history": history}))
+}
+
+pub async fn contest_leaderboard(
+    State(state): State<PgState>
+) -> ApiResponse {
+    let contests = match fetch_contests(&state).await {
+        Ok(contests) => contests,
+        Err(e) => {
+            error!(error = %e, "could not load contests for the leaderboard");
+            return ApiResponse::ServerError
+        }
+    };
+
+    // name -> (contests entered, titles won, sum of average ranks)
+    let mut totals: HashMap<String, (usize, usize, f64)> = HashMap::new();
+
+    for contest in contests {
+        for (name, ranks) in ContestResult::rankings(&contest.roster) {
+            let entry = totals.entry(name).or_insert((0, 0, 0.0));
+            entry.0 += 1;
+            entry.1 += ranks.titles();
+            entry.2 += ranks.average();
+        }
+    }
+
+    let mut leaderboard: Vec<(String, usize, usize, f64)> = totals.into_iter()
+        .map(|(name, (contests, titles, rank_sum))| (name, contests, titles, rank_sum / contests as f64))
+        .collect();
+
+    leaderboard.sort_by(|a, b| {
+        b.2.cmp(&a.2)
+            .then(a.3.total_cmp(&b.3))
+            .then(a.0.cmp(&b.0))
+    });
+
+    let leaderboard: Vec<Value> = leaderboard.into_iter().map(|(name, contests, titles, average_rank)| json!({
+        "name": name,
+        "contests": contests,
+        "titles": titles,
+        "average_rank": average_rank
+    })).collect();
+
+    ApiResponse::JsonValue(json!(leaderboard))
+}
+
 pub async fn slice_query(
+    OriginalUri(uri): OriginalUri,
     Query(pagination): Query<Pagination>,
-    Json(names): Json<Vec<String>>
+    Json(items): Json<Vec<Value>>
 ) -> ApiResponse {
-    let offset: usize = pagination.offset.unwrap_or(0);
+    let page = match pagination.page(items.len()) {
+        Ok(page) => page,
+        Err(reason) => return ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    };
 
-    let limit: usize = pagination.limit.unwrap_or_else(|| names.len() - offset);
+    let selected = &items[page.start..page.end];
 
-    let end_index = std::cmp::min(offset+limit, names.len());
+    let data = match pagination.split {
+        Some(split) => json!(selected.chunks(split).collect::<Vec<_>>()),
+        None => json!(selected)
+    };
 
-    let before_split = &names[offset..end_index];
+    match pagination.meta {
+        Some(PageMeta::Envelope) => ApiResponse::JsonValue(json!({
+            "data": data,
+            "total": page.total,
+            "next_offset": page.next_offset,
+            "pages": page.pages
+        })),
+        Some(PageMeta::Header) => {
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Total-Count", page.total.into());
+            headers.insert("X-Page-Count", page.pages.into());
+
+            if let Some(next_offset) = page.next_offset {
+                let mut link = format!("<{}?offset={}", uri.path(), next_offset);
+                if let Some(limit) = pagination.limit {
+                    link.push_str(&format!("&limit={}", limit));
+                }
+                if let Some(split) = pagination.split {
+                    link.push_str(&format!("&split={}", split));
+                }
+                link.push_str("&meta=header>; rel=\"next\"");
+
+                if let Ok(value) = HeaderValue::from_str(&link) {
+                    headers.insert(LINK, value);
+                }
+            }
 
-    match pagination.split {
-        Some(split) => {
-            let result: Vec<Vec<String>> = before_split.chunks(split).map(|chunk| chunk.to_vec()).collect();
-            ApiResponse::JsonValue(json!(result))
+            ApiResponse::JsonWithHeaders(data, headers)
         },
-        None => ApiResponse::JsonValue(json!(before_split))
+        None => ApiResponse::JsonValue(data)
     }
 }
 
+const ELF_PATTERNS: [&str; 3] = ["elf", "shelf", "elf on a shelf"];
+
 pub async fn count_elf(
+    Query(query): Query<ElfQuery>,
     string: String
 ) -> ApiResponse {
-    let elf_count = string.matches("elf").count();
-    let elf_on_shelf_count = string.matches("elf on a shelf").count();
-    let shelf_count = string.matches("shelf").count();
+    let patterns: Vec<String> = ELF_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+
+    let matches = match find_patterns(&string, &patterns, query.case_insensitive) {
+        Ok(matches) => matches,
+        Err(e) => {
+            error!(error = %e, "elf patterns are invalid");
+            return ApiResponse::ServerError;
+        }
+    };
+
+    let starts = |pattern: &str| -> HashSet<usize> {
+        matches.iter()
+            .filter(|found| found.pattern == pattern)
+            .map(|found| found.start)
+            .collect()
+    };
+
+    let elf_count = starts("elf").len();
+    let elf_on_shelf = starts("elf on a shelf");
+    let shelves = starts("shelf");
+
+    // a shelf has an elf on it when "elf on a " ends right where it starts
+    let empty_shelves = shelves.iter()
+        .filter(|&&start| start < 9 || !elf_on_shelf.contains(&(start - 9)))
+        .count();
 
     ApiResponse::JsonValue(json!({
         "elf": elf_count,
-        "elf on a shelf": elf_on_shelf_count,
-        "shelf with no elf on it": shelf_count - elf_on_shelf_count
+        "elf on a shelf": elf_on_shelf.len(),
+        "shelf with no elf on it": empty_shelves
+    }))
+}
+
+pub async fn count_patterns(
+    Json(request): Json<PatternRequest>
+) -> ApiResponse {
+    let mut patterns: Vec<String> = Vec::new();
+    for pattern in request.patterns {
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+
+    let matches = match find_patterns(&request.text, &patterns, request.case_insensitive) {
+        Ok(matches) => matches,
+        Err(reason) => return ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    };
+
+    let mut counts: BTreeMap<&str, usize> = patterns.iter().map(|pattern| (pattern.as_str(), 0)).collect();
+    for found in &matches {
+        if let Some(count) = counts.get_mut(found.pattern.as_str()) {
+            *count += 1;
+        }
+    }
+
+    ApiResponse::JsonValue(json!({
+        "counts": counts,
+        "matches": matches
     }))
 }
 
+const MAX_TOP: usize = 1000;
+
+pub async fn text_stats(
+    Query(query): Query<StatsQuery>,
+    State(config): State<ConfigStore>,
+    body: BodyStream
+) -> ApiResponse {
+    let top = query.top.unwrap_or(10);
+    if top > MAX_TOP {
+        return ApiResponse::RequestErrorAndJson(json!({"error": format!("top cannot exceed {}", MAX_TOP)}));
+    }
+
+    let ngram = query.ngram.unwrap_or(2);
+    if ngram == 0 || ngram > MAX_NGRAM {
+        return ApiResponse::RequestErrorAndJson(json!({"error": format!("ngram must be between 1 and {}", MAX_NGRAM)}));
+    }
+
+    let patterns: Vec<String> = match query.patterns {
+        Some(patterns) => patterns.split(',').map(|pattern| pattern.to_string()).collect(),
+        None => ELF_PATTERNS.iter().map(|pattern| pattern.to_string()).collect()
+    };
+
+    let matcher = match pattern_matcher(&patterns, query.case_insensitive) {
+        Ok(matcher) => matcher,
+        Err(reason) => return ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    };
+
+    match collect_text_stats(body, config.config.body_limit, TextStats::new(ngram, patterns, Some(matcher))).await {
+        Ok(stats) => ApiResponse::JsonValue(json!(stats.report(top))),
+        Err(reason) => ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    }
+}
+
 pub async fn decode_header(
     headers: HeaderMap
 ) -> ApiResponse {
@@ -134,8 +673,6 @@ pub async fn bake_recipe(
         None => return ApiResponse::ServerError
     };
 
-    println!("{}", string);
-
     let mut full_recipe: Value = serde_json::from_str(&string).unwrap();
     
     let full_recipe_object = full_recipe.as_object_mut().unwrap();
@@ -143,7 +680,10 @@ pub async fn bake_recipe(
 
     let recipe = full_recipe_clone.get("recipe").unwrap().as_object().unwrap();
     let pantry =  full_recipe_object.get_mut("pantry").unwrap().as_object_mut().unwrap();
-    
+
+    // the recipe and pantry are user data, so only their sizes are logged
+    debug!(ingredients = recipe.len(), pantry_items = pantry.len(), "baking");
+
     let mut ratios: Vec<u64> = Vec::new();
 
     for (key, value) in pantry.iter() {
@@ -160,8 +700,7 @@ pub async fn bake_recipe(
 
     }
 
-    println!("{}", min_ratio);
-    println!("{:?}", pantry);
+    debug!(cookies = min_ratio, "baked");
 
     ApiResponse::JsonValue(json!({
         "cookies": min_ratio,
@@ -169,35 +708,70 @@ pub async fn bake_recipe(
     }))
 }
 
-pub async fn pokemon_weight(
-    Path(id): Path<u64>
-) -> ApiResponse {
-    println!("{}", id);
-    let body = match reqwest::get(format!("https://pokeapi.co/api/v2/pokemon/{}", id)).await.unwrap().text().await {
-        Ok(r) => r,
-        Err(_) => return ApiResponse::ServerError
+async fn fetch_pokemon_weight(pokeapi: &PokeApiState, id: u64) -> Result<f64, ApiResponse> {
+    let url = format!("{}/pokemon/{}", pokeapi.base_url, id);
+    let started = Instant::now();
+
+    let response = match pokeapi.client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            pokeapi.metrics.observe_pokeapi("error", started);
+            error!(error = %e, url = %url, "pokeapi request failed");
+            return Err(ApiResponse::ServerError);
+        }
     };
 
-    let pokemon: Value = serde_json::from_str(&body).unwrap();
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        pokeapi.metrics.observe_pokeapi("not_found", started);
+        return Err(ApiResponse::NotFound);
+    }
 
-    let weight = pokemon.get("weight").unwrap().as_f64().unwrap();
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => {
+            pokeapi.metrics.observe_pokeapi("error", started);
+            error!(error = %e, url = %url, "could not read pokeapi response");
+            return Err(ApiResponse::ServerError);
+        }
+    };
+    pokeapi.metrics.observe_pokeapi("ok", started);
 
-    let weight = weight / 10.0;
+    let pokemon: Value = match serde_json::from_str(&body) {
+        Ok(pokemon) => pokemon,
+        Err(e) => {
+            error!(error = %e, url = %url, "pokeapi returned invalid json");
+            return Err(ApiResponse::ServerError);
+        }
+    };
 
-    ApiResponse::String(weight.to_string())
+    match pokemon.get("weight").and_then(Value::as_f64) {
+        Some(weight) => Ok(weight / 10.0),
+        None => {
+            error!(id, "pokemon has no weight");
+            Err(ApiResponse::ServerError)
+        }
+    }
+}
+
+pub async fn pokemon_weight(
+    State(pokeapi): State<PokeApiState>,
+    Path(id): Path<u64>
+) -> ApiResponse {
+    match fetch_pokemon_weight(&pokeapi, id).await {
+        Ok(weight) => ApiResponse::String(weight.to_string()),
+        Err(response) => response
+    }
 }
 
 pub async fn pokemon_momentum(
+    State(pokeapi): State<PokeApiState>,
     Path(id): Path<u64>
 ) -> ApiResponse {
-    let body = match reqwest::get(format!("https://pokeapi.co/api/v2/pokemon/{}", id)).await.unwrap().text().await {
-        Ok(r) => r,
-        Err(_) => return ApiResponse::ServerError
+    let weight = match fetch_pokemon_weight(&pokeapi, id).await {
+        Ok(weight) => weight,
+        Err(response) => return response
     };
 
-    let pokemon: Value = serde_json::from_str(&body).unwrap();
-    let weight = pokemon.get("weight").unwrap().as_f64().unwrap();
-    let weight = weight / 10.0;
     let velocity = (2.0_f64 * 9.825 * 10.0).sqrt();
     let momentum = velocity * weight;
 
@@ -205,12 +779,20 @@ pub async fn pokemon_momentum(
 }
 
 pub async fn serve_image(
+    State(config): State<ConfigStore>,
     Path(path): Path<String>
 ) -> ApiResponse {
-    match fs::read(format!("/home/sebatustra/rust/shuttle-christmas/assets/{}", path)).await {
+    // the segment is percent-decoded, so "..%2F" must not walk out of the root
+    let relative = std::path::Path::new(&path);
+    if !relative.components().all(|component| matches!(component, std::path::Component::Normal(_))) {
+        return ApiResponse::NotFound;
+    }
+
+    match fs::read(config.config.asset_root.join(relative)).await {
         Ok(image_data) => ApiResponse::PngImage(image_data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ApiResponse::NotFound,
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, path = %path, "could not read asset");
             ApiResponse::ServerError
         }
     }
@@ -234,13 +816,13 @@ pub async fn read_pixels(
                         }
                     }
 
-                    return ApiResponse::Unsigned(counter)
+                    ApiResponse::Unsigned(counter)
                 },
-                None => return ApiResponse::ServerError
+                None => ApiResponse::ServerError
             }
         },
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, "could not read multipart upload");
             ApiResponse::ServerError
         }
     }
@@ -283,8 +865,7 @@ pub async fn handle_ulids(
 
     let mut uuids: Vec<Uuid> = ulids.iter().map(|ulid| {
         let bytes = ulid.to_bytes();
-        let uuid = Uuid::from_bytes(bytes);
-        uuid
+        Uuid::from_bytes(bytes)
     }).collect();
 
     uuids.reverse();
@@ -350,27 +931,61 @@ pub async fn safe_render(
 }
 
 pub async fn check_password(
+    State(store): State<PolicyStore>,
     Json(password): Json<Password>
 ) -> ApiResponse {
-    let input_lower = password.input;
-    
-    let has_three_vowels = input_lower.chars()
-    .filter(|c| "aeiouy".contains(*c))
-    .count() >= 3;
+    classify_password("default", &store, &password.input)
+}
 
-let has_double_letter = input_lower.as_bytes()
-.windows(2)
-.any(|w| w[0] == w[1]);
+pub async fn check_password_with_policy(
+    Path(policy_name): Path<String>,
+    State(store): State<PolicyStore>,
+    Json(password): Json<Password>
+) -> ApiResponse {
+    classify_password(&policy_name, &store, &password.input)
+}
 
-let no_forbidden_substrings = !["ab", "cd", "pq", "xy"]
-.iter()
-.any(|&s| input_lower.contains(s));
+fn classify_password(policy_name: &str, store: &PolicyStore, input: &str) -> ApiResponse {
+    let policy = match store.policies.get(policy_name) {
+        Some(policy) => policy,
+        None => return ApiResponse::NotFound
+    };
 
-if has_three_vowels && has_double_letter && no_forbidden_substrings {
-    ApiResponse::JsonValue(json!({"result": "nice"}))
-} else {
-    ApiResponse::RequestErrorAndJson(json!({"result": "naughty"}))
+    if policy.is_nice(input) {
+        ApiResponse::JsonValue(json!({"result": "nice"}))
+    } else {
+        ApiResponse::RequestErrorAndJson(json!({"result": "naughty"}))
+    }
 }
+
+pub async fn batch_passwords(
+    Path(policy_name): Path<String>,
+    State(store): State<PolicyStore>,
+    Json(batch): Json<PasswordBatch>
+) -> ApiResponse {
+    let policy = match store.policies.get(&policy_name) {
+        Some(policy) => policy,
+        None => return ApiResponse::NotFound
+    };
+
+    let mut nice_count: u64 = 0;
+
+    let results: Vec<Value> = batch.inputs.iter().map(|input| {
+        let result = if policy.is_nice(input) {
+            nice_count += 1;
+            "nice"
+        } else {
+            "naughty"
+        };
+        json!({"input": input, "result": result})
+    }).collect();
+
+    ApiResponse::JsonValue(json!({
+        "policy": policy_name,
+        "nice": nice_count,
+        "naughty": results.len() as u64 - nice_count,
+        "results": results
+    }))
 }
 
 pub async fn game_password(
@@ -404,7 +1019,9 @@ if sum != 2023 {
 }
 
 //5
-if !Regex::new(r"(.)\1").unwrap().is_match(content_str) {
+// the regex crate has no backreferences, so look for the doubled letter directly
+let chars: Vec<char> = content_str.chars().collect();
+if !chars.windows(2).any(|pair| pair[0] == pair[1]) {
     return StatusCode::NOT_ACCEPTABLE
 }
 
@@ -435,17 +1052,32 @@ if format!("{:x}", hash).chars().last().unwrap() != 'a' {
 StatusCode::OK
 }
 
+pub async fn password_strength(
+    State(store): State<BreachStore>,
+    Json(password): Json<Password>
+) -> ApiResponse {
+    let length = password.input.chars().count();
+    if length > MAX_PASSWORD_LENGTH {
+        let reason = format!("password is {} characters, the limit is {}", length, MAX_PASSWORD_LENGTH);
+        return ApiResponse::RequestErrorAndJson(json!({"error": reason}));
+    }
+
+    let report = StrengthReport::evaluate(&password.input, &store.hashes);
+
+    ApiResponse::JsonValue(json!(report))
+}
+
 pub async fn dumb_query(
     State(state): State<PgState>
 ) -> ApiResponse {
-    match sqlx::query!("SELECT 20231213 AS value;")
+    match sqlx::query_scalar::<_, i32>("SELECT 20231213 AS value;")
         .fetch_one(&state.pool)
         .await {
-            Ok(result) => {
-                ApiResponse::String(result.value.unwrap().to_string())
+            Ok(value) => {
+                ApiResponse::String(value.to_string())
             },
             Err(e) => {
-                println!("{}", e);
+                error!(error = %e, "could not run sql query");
                 ApiResponse::ServerError
             }
         }
@@ -455,45 +1087,42 @@ pub async fn reset_db(
     State(state): State<PgState>
 ) -> ApiResponse {
 
-    match sqlx::query!("DROP TABLE IF EXISTS regions;").execute(&state.pool).await {
+    match sqlx::query("DROP TABLE IF EXISTS regions;").execute(&state.pool).await {
         Ok(_) => {
-            println!("dropped previous regions table");
-            ()
+            debug!("dropped previous regions table");
         },
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, "could not drop regions table");
             return ApiResponse::ServerError
         }
     }
 
-    match sqlx::query!("DROP TABLE IF EXISTS orders;").execute(&state.pool).await {
+    match sqlx::query("DROP TABLE IF EXISTS orders;").execute(&state.pool).await {
         Ok(_) => {
-            println!("dropped previous orders table");
-            ()
+            debug!("dropped previous orders table");
         },
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, "could not drop orders table");
             return ApiResponse::ServerError
         }
     }
 
-    match sqlx::query!("
+    match sqlx::query("
         CREATE TABLE regions (
         id INT PRIMARY KEY,
         name VARCHAR(50)
         );
     ").execute(&state.pool).await {
         Ok(_) => {
-            println!("created new regions table");
-            ()
+            debug!("created new regions table");
         },
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, "could not create regions table");
             return ApiResponse::ServerError
         }
     }
 
-    match sqlx::query!("
+    match sqlx::query("
         CREATE TABLE orders (
         id INT PRIMARY KEY,
         region_id INT,
@@ -502,12 +1131,12 @@ pub async fn reset_db(
         );
     ").execute(& state.pool).await {
         Ok(_) => {
-            println!("created new orders table");
-            return ApiResponse::Ok
+            debug!("created new orders table");
+            ApiResponse::Ok
         }
         Err(e) => {
-            println!("{:?}", e);
-            return ApiResponse::ServerError
+            error!(error = %e, "could not create orders table");
+            ApiResponse::ServerError
         }
     }
 }
@@ -528,17 +1157,16 @@ pub async fn insert_orders(
         .execute(&state.pool)
         .await {
             Ok(_) => {
-                println!("inserted order with id {}.", order.id);
-                ()
+                debug!(id = order.id, "inserted order");
             },
             Err(e) => {
-                println!("{:?}", e);
+                error!(error = %e, "could not insert order");
                 return ApiResponse::ServerError
             }
         }
     }
 
-    return ApiResponse::Ok
+    ApiResponse::Ok
 }
 
 pub async fn insert_regions(
@@ -554,17 +1182,16 @@ pub async fn insert_regions(
         .execute(&state.pool)
         .await {
             Ok(_) => {
-                println!("inserted region with id {}.", region.id);
-                ()
+                debug!(id = region.id, "inserted region");
             },
             Err(e) => {
-                println!("{:?}", e);
+                error!(error = %e, "could not insert region");
                 return ApiResponse::ServerError
             }
         }
     }
 
-    return ApiResponse::Ok
+    ApiResponse::Ok
 }
 
 pub async fn total_regions(
@@ -583,7 +1210,7 @@ pub async fn total_regions(
     .await {
         Ok(result) => ApiResponse::JsonValue(json!(result)),
         Err(e) => {
-            println!("{}", e);
+            error!(error = %e, "could not total regions");
             ApiResponse::ServerError
         }
     }
@@ -599,7 +1226,7 @@ pub async fn total_orders(
     .await {
         Ok(result) => ApiResponse::JsonValue(json!({"total": result})),
         Err(e) => {
-            println!("{}", e);
+            error!(error = %e, "could not total orders");
             ApiResponse::ServerError
         }
     }
@@ -620,36 +1247,346 @@ pub async fn popular_order(
             }
         },
         Err(e) => {
-            println!("{:?}", e);
+            error!(error = %e, "could not find popular order");
             ApiResponse::ServerError
         }
     }
 }
 
-pub async fn handler_sockets(ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(handle_socket)
+pub async fn handler_sockets(
+    State(store): State<MetricsStore>,
+    ws: WebSocketUpgrade
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, store))
 }
 
-async fn handle_socket(mut socket: WebSocket) {
+async fn handle_socket(mut socket: WebSocket, store: MetricsStore) {
+    let _connection = store.metrics.websocket_opened("ping");
+    let mut game = PingPongGame::default();
+
     while let Some(msg) = socket.recv().await {
-        let msg = if let Ok(msg) = msg {
-            match &msg {
-                Message::Text(text) => {
-                    if text.eq("serve") {
-                        socket.send("hola".into());
-                        msg
-                    } else {
-                        return
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(_) => return
+        };
+
+        match msg {
+            Message::Text(text) => {
+                if let Some(reply) = game.play(&text) {
+                    if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            },
+            // tungstenite queues the Pong for every Ping and the reply to a
+            // Close, the next recv() flushes them and then returns None
+            Message::Ping(_) | Message::Pong(_) | Message::Binary(_) | Message::Close(_) => ()
+        }
+    }
+}
+
+pub async fn chat_room(
+    Path((room, user)): Path<(String, String)>,
+    Query(query): Query<ChatQuery>,
+    State(chat): State<ChatState>,
+    State(pg): State<PgState>,
+    State(store): State<MetricsStore>,
+    ws: WebSocketUpgrade
+) -> Response {
+    let history = std::cmp::min(query.history.unwrap_or(chat.replay_window), MAX_CHAT_PAGE);
+
+    ws.on_upgrade(move |socket| async move {
+        let _connection = store.metrics.websocket_opened("chat");
+        handle_chat(socket, room, user, history, chat, pg).await
+    })
+}
+
+const MAX_CHAT_PAGE: usize = 100;
+
+async fn handle_chat(
+    socket: WebSocket,
+    room: String,
+    user: String,
+    history: usize,
+    chat: ChatState,
+    pg: PgState
+) {
+    // subscribing before reading the history means none sent while we query
+    // can be lost, and anything that also made it into the replay is skipped
+    // by id once the live feed starts
+    let (sender, mut receiver) = chat.join(&room).await;
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let mut last_replayed: Option<Ulid> = None;
+    if history > 0 {
+        match fetch_chat_page(&pg, &room, None, history).await {
+            Ok(mut messages) => {
+                messages.reverse();
+                for stored in messages {
+                    let text = json!({"user": stored.user, "message": stored.message}).to_string();
+                    if ws_sender.send(Message::Text(text)).await.is_err() {
+                        drop(receiver);
+                        chat.leave(&room).await;
+                        return;
+                    }
+                    if let Ok(id) = Ulid::from_string(&stored.id) {
+                        last_replayed = Some(id);
+                    }
+                }
+            },
+            Err(e) => error!(error = %e, room = %room, "could not replay chat history")
+        }
+    }
+
+    let views = chat.views.clone();
+    let mut send_task = tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(chat_message) => {
+                    if last_replayed.is_some_and(|last| chat_message.id <= last) {
+                        continue;
+                    }
+                    let text = json!(chat_message).to_string();
+                    if ws_sender.send(Message::Text(text)).await.is_err() {
+                        break;
                     }
+                    views.fetch_add(1, Ordering::Relaxed);
                 },
-                _ => return,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break
             }
-        } else {
-            return
-        };
+        }
+    });
+
+    let chat_ids = chat.clone();
+    let room_name = room.clone();
+    let mut recv_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = ws_receiver.next().await {
+            let text = match msg {
+                Message::Text(text) => text,
+                _ => continue
+            };
+
+            let incoming: IncomingChat = match serde_json::from_str(&text) {
+                Ok(incoming) => incoming,
+                Err(_) => continue
+            };
+
+            if incoming.message.chars().count() > 128 {
+                continue;
+            }
+
+            let id = chat_ids.next_id().await;
+            let chat_message = ChatMessage {
+                id,
+                user: user.clone(),
+                message: incoming.message
+            };
+            let _ = sender.send(chat_message.clone());
+
+            if let Err(e) = sqlx::query(
+                "INSERT INTO chat_messages (id, room, username, message) VALUES ($1, $2, $3, $4);"
+            )
+            .bind(id.to_string())
+            .bind(&room_name)
+            .bind(&chat_message.user)
+            .bind(&chat_message.message)
+            .execute(&pg.pool)
+            .await {
+                error!(error = %e, room = %room_name, "could not persist chat message");
+            }
+        }
+    });
+
+    // an aborted task only drops its receiver once it has been awaited, and
+    // until then leave would still count it as a subscriber
+    tokio::select! {
+        _ = &mut send_task => {
+            recv_task.abort();
+            let _ = recv_task.await;
+        },
+        _ = &mut recv_task => {
+            send_task.abort();
+            let _ = send_task.await;
+        }
+    }
+
+    chat.leave(&room).await;
+}
+
+async fn fetch_chat_page(
+    pg: &PgState,
+    room: &str,
+    before: Option<Ulid>,
+    limit: usize
+) -> Result<Vec<StoredChatMessage>, sqlx::Error> {
+    // ULIDs sort lexicographically by creation time, so the id doubles as
+    // the cursor. Without one there is no bound at all: a sentinel like "~"
+    // only sorts after every ULID under byte collation.
+    let query = match before {
+        Some(ulid) => sqlx::query_as::<_, StoredChatMessage>("
+            SELECT id, username, message
+            FROM chat_messages
+            WHERE room = $1 AND id < $2
+            ORDER BY id DESC
+            LIMIT $3;
+        ")
+        .bind(room)
+        .bind(ulid.to_string())
+        .bind(limit as i64),
+        None => sqlx::query_as::<_, StoredChatMessage>("
+            SELECT id, username, message
+            FROM chat_messages
+            WHERE room = $1
+            ORDER BY id DESC
+            LIMIT $2;
+        ")
+        .bind(room)
+        .bind(limit as i64)
+    };
+
+    query.fetch_all(&pg.pool).await
+}
+
+pub async fn chat_history(
+    Path(room): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(pg): State<PgState>
+) -> ApiResponse {
+    let before = match query.before {
+        Some(cursor) => match Ulid::from_string(&cursor) {
+            Ok(ulid) => Some(ulid),
+            Err(_) => return ApiResponse::RequestErrorAndJson(json!({"error": "invalid cursor"}))
+        },
+        None => None
+    };
+
+    let limit = std::cmp::min(query.limit.unwrap_or(20), MAX_CHAT_PAGE);
+
+    match fetch_chat_page(&pg, &room, before, limit).await {
+        Ok(messages) => {
+            let next = if limit > 0 && messages.len() == limit {
+                messages.last().map(|stored| stored.id.clone())
+            } else {
+                None
+            };
+
+            ApiResponse::JsonValue(json!({
+                "room": room,
+                "messages": messages,
+                "next": next
+            }))
+        },
+        Err(e) => {
+            error!(error = %e, room = %room, "could not load chat history");
+            ApiResponse::ServerError
+        }
+    }
+}
 
-        if socket.send(msg).await.is_err() {
-            return;
+pub async fn chat_views(
+    State(chat): State<ChatState>
+) -> ApiResponse {
+    ApiResponse::Unsigned(chat.views.load(Ordering::Relaxed))
+}
+
+pub async fn reset_views(
+    State(chat): State<ChatState>
+) -> ApiResponse {
+    chat.views.store(0, Ordering::Relaxed);
+
+    ApiResponse::Ok
+}
+
+pub async fn archive_files(
+    State(config): State<ConfigStore>,
+    body: BodyStream
+) -> ApiResponse {
+    match summarize_tar(body, config.config.body_limit).await {
+        Ok(summary) => ApiResponse::Unsigned(summary.files),
+        Err(reason) => ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    }
+}
+
+pub async fn archive_files_size(
+    State(config): State<ConfigStore>,
+    body: BodyStream
+) -> ApiResponse {
+    match summarize_tar(body, config.config.body_limit).await {
+        Ok(summary) => ApiResponse::Unsigned(summary.total_size),
+        Err(reason) => ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    }
+}
+
+pub async fn cookie_hunt(
+    body: Bytes
+) -> ApiResponse {
+    match tokio::task::spawn_blocking(move || find_cookie(&body)).await {
+        Ok(Ok(Some((author, hash)))) => ApiResponse::String(format!("{} {}", author, hash)),
+        Ok(Ok(None)) => ApiResponse::NotFound,
+        Ok(Err(reason)) => ApiResponse::RequestErrorAndJson(json!({"error": reason})),
+        Err(e) => {
+            error!(error = %e, "cookie hunt panicked");
+            ApiResponse::ServerError
         }
     }
-}   
+}
+
+pub async fn s2_coords(
+    Path(binary): Path<String>
+) -> ApiResponse {
+    let cell_id = match u64::from_str_radix(&binary, 2) {
+        Ok(cell_id) if is_valid_cell(cell_id) => cell_id,
+        _ => return ApiResponse::RequestErrorAndJson(json!({"error": "invalid S2 cell id"}))
+    };
+
+    let (lat, lng) = cell_center(cell_id);
+
+    ApiResponse::String(format_dms(lat, lng))
+}
+
+pub async fn s2_country(
+    Path(binary): Path<String>,
+    State(countries): State<CountryStore>
+) -> ApiResponse {
+    let cell_id = match u64::from_str_radix(&binary, 2) {
+        Ok(cell_id) if is_valid_cell(cell_id) => cell_id,
+        _ => return ApiResponse::RequestErrorAndJson(json!({"error": "invalid S2 cell id"}))
+    };
+
+    let (lat, lng) = cell_center(cell_id);
+
+    match countries.index.lookup(lat, lng) {
+        Some(country) => ApiResponse::String(country.to_string()),
+        None => ApiResponse::NotFound
+    }
+}
+
+const MAX_PRESENTS: u64 = 1 << 20;
+
+pub async fn lonely_integer(
+    State(config): State<ConfigStore>,
+    body: BodyStream
+) -> ApiResponse {
+    match xor_lines(body, config.config.body_limit).await {
+        Ok(number) if number > MAX_PRESENTS => ApiResponse::RequestErrorAndJson(json!({
+            "error": format!("{} presents do not fit under the tree", number)
+        })),
+        Ok(number) => ApiResponse::String("🎁".repeat(number as usize)),
+        Err(reason) => ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    }
+}
+
+pub async fn rocket_route(
+    body: String
+) -> ApiResponse {
+    let star_map = match StarMap::parse(&body) {
+        Ok(star_map) => star_map,
+        Err(reason) => return ApiResponse::RequestErrorAndJson(json!({"error": reason}))
+    };
+
+    match star_map.shortest_route() {
+        Some((portals, distance)) => ApiResponse::String(format!("{} {:.3}", portals, distance)),
+        None => ApiResponse::NotFound
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::HashSet, future::Future, path::Path, time::Duration};
+
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use tokio::{fs, time::timeout};
+
+// A probe has to answer well before its own deadline, so a check that hangs
+// (an exhausted pool, a stuck mount) reports an error instead.
+const CHECK_TIMEOUT: Duration = Duration::from_millis(1500);
+
+pub async fn check_database(pool: &PgPool) -> Result<(), String> {
+    match sqlx::query("SELECT 1").execute(pool).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("could not query postgres: {}", e))
+    }
+}
+
+/// Compares the migrations bundled into the binary with the versions sqlx
+/// recorded as applied.
+pub async fn check_migrations(pool: &PgPool) -> Result<(), String> {
+    let applied: HashSet<i64> = match sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+        .fetch_all(pool)
+        .await {
+            Ok(versions) => versions.into_iter().collect(),
+            Err(e) => return Err(format!("could not read applied migrations: {}", e))
+        };
+
+    let pending: Vec<String> = sqlx::migrate!()
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| migration.version.to_string())
+        .collect();
+
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("pending migrations: {}", pending.join(", ")))
+    }
+}
+
+pub async fn check_assets(root: &Path) -> Result<(), String> {
+    let mut entries = match fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("could not read {}: {}", root.display(), e))
+    };
+
+    match entries.next_entry().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("could not list {}: {}", root.display(), e))
+    }
+}
+
+async fn bounded(check: impl Future<Output = Result<(), String>>) -> Result<(), String> {
+    match timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {} ms", CHECK_TIMEOUT.as_millis()))
+    }
+}
+
+/// Runs every readiness check concurrently, returning whether all passed and
+/// a per-component breakdown.
+pub async fn readiness(pool: &PgPool, asset_root: &Path) -> (bool, Value) {
+    let (database, migrations, assets) = tokio::join!(
+        bounded(check_database(pool)),
+        bounded(check_migrations(pool)),
+        bounded(check_assets(asset_root))
+    );
+    let checks = vec![
+        ("database", database),
+        ("migrations", migrations),
+        ("assets", assets)
+    ];
+
+    let ready = checks.iter().all(|(_, result)| result.is_ok());
+    let mut components = Map::new();
+    for (name, result) in checks {
+        let component = match result {
+            Ok(()) => json!({"status": "ok"}),
+            Err(reason) => json!({"status": "error", "error": reason})
+        };
+        components.insert(name.to_string(), component);
+    }
+
+    let status = if ready { "ready" } else { "not_ready" };
+    (ready, json!({"status": status, "checks": components}))
+}
@@ -0,0 +1,259 @@
+mod handlers;
+mod types;
+mod structs;
+mod utils;
+mod s2;
+pub mod config;
+pub mod geo;
+pub mod health;
+pub mod metrics;
+pub mod state;
+pub mod validator;
+
+use std::{sync::Arc, time::Duration};
+use axum::{
+    body::Body,
+    extract::{DefaultBodyLimit, MatchedPath},
+    http::{Request, Response},
+    middleware,
+    routing::{get, post},
+    Router
+};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer
+};
+use tracing::{error, field, info, info_span, Span};
+use tracing_subscriber::EnvFilter;
+use handlers::{
+    fake_error, 
+    export_metrics,
+    liveness,
+    readiness,
+    bits_expression, 
+    reindeer_strength, 
+    reindeer_contest, 
+    contest_analytics, 
+    register_reindeer, 
+    list_reindeer, 
+    get_reindeer, 
+    update_reindeer, 
+    delete_reindeer, 
+    reindeer_history, 
+    create_contest, 
+    list_contests, 
+    get_contest, 
+    contest_leaderboard, 
+    slice_query, 
+    count_elf, 
+    count_patterns,
+    text_stats,
+    decode_header, 
+    bake_recipe, 
+    pokemon_weight, 
+    pokemon_momentum, 
+    serve_image, 
+    read_pixels, 
+    // save_packet, 
+    // load_packet, 
+    handle_ulids, 
+    analize_ulids, 
+    dumb_query, 
+    reset_db, 
+    insert_orders, 
+    total_orders, 
+    popular_order, 
+    unsafe_render, safe_render, check_password, check_password_with_policy, batch_passwords, game_password, password_strength, insert_regions, total_regions, handler_sockets, chat_room, chat_views, reset_views, chat_history, archive_files, archive_files_size, cookie_hunt, s2_coords, s2_country, lonely_integer, rocket_route
+};
+use state::{
+    // IdStore, 
+    PgState,
+    PolicyStore,
+    BreachStore,
+    ChatState,
+    CountryStore,
+    ConfigStore,
+    PokeApiState,
+    MetricsStore,
+    AppState
+};
+use config::{AppConfig, LogFormat};
+use utils::{load_policies, load_breached_passwords};
+use geo::CountryIndex;
+use metrics::{track_requests, Metrics};
+// use tokio::sync::Mutex;
+use sqlx::PgPool;
+
+// RUST_LOG picks the levels, e.g. "info,sebatustra_christmas=debug"
+pub fn init_tracing(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Json => subscriber.json().flatten_event(true).with_current_span(true).with_span_list(false).init(),
+        LogFormat::Text => subscriber.init()
+    }
+}
+
+pub async fn migrate(pool: &PgPool) -> Result<(), String> {
+    match sqlx::migrate!().run(pool).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("could not run migrations: {}", e))
+    }
+}
+
+pub fn build_state(pool: PgPool, config: AppConfig) -> Result<AppState, String> {
+    // let state = IdStore {
+    //     store: Arc::new(Mutex::new(Vec::new()))
+    // };
+
+    let policies = load_policies(&config.policies_path)?;
+    let breached = load_breached_passwords(&config.breached_path)?;
+    let countries = CountryIndex::bundled()?;
+    let metrics = Arc::new(Metrics::new()?);
+
+    Ok(AppState {
+        pg: PgState { pool },
+        policies: PolicyStore { policies: Arc::new(policies) },
+        breached: BreachStore { hashes: Arc::new(breached) },
+        chat: ChatState::new(config.chat_replay_window),
+        countries: CountryStore { index: Arc::new(countries) },
+        pokeapi: PokeApiState::new(&config, metrics.clone())?,
+        metrics: MetricsStore { metrics },
+        config: ConfigStore { config: Arc::new(config) }
+    })
+}
+
+/// Every challenge route, minus the days switched off in the config.
+pub fn build_router(state: AppState) -> Router {
+    let config = state.config.config.clone();
+
+    let days: Vec<(i32, Router<AppState>)> = vec![
+        (-1, Router::new()
+            .route("/-1/error", get(fake_error))),
+        (1, Router::new()
+            .route("/1/*nums", get(bits_expression))),
+        (4, Router::new()
+            .route("/4/strength", post(reindeer_strength))
+            .route("/4/contest", post(reindeer_contest))
+            .route("/4/contest/analytics", post(contest_analytics))
+            .route("/4/reindeer", get(list_reindeer).post(register_reindeer))
+            .route("/4/reindeer/:name", get(get_reindeer).put(update_reindeer).delete(delete_reindeer))
+            .route("/4/reindeer/:name/history", get(reindeer_history))
+            .route("/4/contests", get(list_contests).post(create_contest))
+            .route("/4/contests/:id", get(get_contest))
+            .route("/4/leaderboard", get(contest_leaderboard))),
+        (5, Router::new()
+            .route("/5", post(slice_query))),
+        (6, Router::new()
+            .route("/6", post(count_elf))
+            .route("/6/patterns", post(count_patterns))
+            .route("/6/stats", post(text_stats))),
+        (7, Router::new()
+            .route("/7/decode", get(decode_header))
+            .route("/7/bake", get(bake_recipe))),
+        (8, Router::new()
+            .route("/8/weight/:id", get(pokemon_weight))
+            .route("/8/drop/:id", get(pokemon_momentum))),
+        (11, Router::new()
+            .route("/11/assets/:path", get(serve_image))
+            .route("/11/red_pixels", post(read_pixels))),
+        (12, Router::new()
+            // .route("/12/save/:packet_id", post(save_packet))
+            // .route("/12/load/:packet_id", get(load_packet))
+            .route("/12/ulids", post(handle_ulids))
+            .route("/12/ulids/:day", post(analize_ulids))),
+        (13, Router::new()
+            .route("/13/sql", get(dumb_query))
+            .route("/13/orders/total", get(total_orders))
+            .route("/13/orders/popular", get(popular_order))),
+        (14, Router::new()
+            .route("/14/unsafe", post(unsafe_render))
+            .route("/14/safe", post(safe_render))),
+        (15, Router::new()
+            .route("/15/nice", post(check_password))
+            .route("/15/nice/:policy", post(check_password_with_policy))
+            .route("/15/nice/:policy/batch", post(batch_passwords))
+            .route("/15/game", post(game_password))
+            .route("/15/strength", post(password_strength))),
+        (18, Router::new()
+            .route("/18/reset", post(reset_db))
+            .route("/18/orders", post(insert_orders))
+            .route("/18/regions", post(insert_regions))
+            .route("/18/regions/total", get(total_regions))),
+        (19, Router::new()
+            .route("/19/ws/ping", get(handler_sockets))
+            .route("/19/ws/room/:room/user/:user", get(chat_room))
+            .route("/19/views", get(chat_views))
+            .route("/19/reset", post(reset_views))
+            .route("/19/history/:room", get(chat_history))),
+        (20, Router::new()
+            .route("/20/archive_files", post(archive_files))
+            .route("/20/archive_files_size", post(archive_files_size))
+            .route("/20/cookie", post(cookie_hunt))),
+        (21, Router::new()
+            .route("/21/coords/:binary", get(s2_coords))
+            .route("/21/country/:binary", get(s2_country))),
+        (22, Router::new()
+            .route("/22/integers", post(lonely_integer))
+            .route("/22/rocket", post(rocket_route)))
+    ];
+
+    // served whichever days are switched off
+    let mut router = Router::new()
+        .route("/metrics", get(export_metrics))
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness));
+    for (day, routes) in days {
+        if config.day_enabled(day) {
+            router = router.merge(routes);
+        } else {
+            info!(day, "day is disabled");
+        }
+    }
+
+    // layers run bottom to top, so the id is set before the span reads it
+    router
+        .layer(DefaultBodyLimit::max(config.body_limit))
+        .layer(middleware::from_fn_with_state(state.metrics.clone(), track_requests))
+        .layer(TraceLayer::new_for_http()
+            .make_span_with(request_span)
+            .on_request(())
+            .on_response(record_response)
+            .on_failure(()))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state)
+}
+
+fn request_span(request: &Request<Body>) -> Span {
+    let route = request.extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or("unmatched");
+    let request_id = request.headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("-");
+
+    info_span!(
+        "request",
+        id = %request_id,
+        method = %request.method(),
+        route,
+        status = field::Empty,
+        latency_ms = field::Empty
+    )
+}
+
+fn record_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_millis() as u64);
+
+    if response.status().is_server_error() {
+        error!("request failed");
+    } else {
+        info!("request finished");
+    }
+}
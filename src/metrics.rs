@@ -0,0 +1,157 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response
+};
+use prometheus::{
+    Encoder,
+    HistogramOpts,
+    HistogramVec,
+    IntCounterVec,
+    IntGauge,
+    IntGaugeVec,
+    Opts,
+    Registry,
+    TextEncoder
+};
+use sqlx::PgPool;
+
+use crate::state::MetricsStore;
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    request_errors: IntCounterVec,
+    pool_connections: IntGauge,
+    pool_idle_connections: IntGauge,
+    pool_max_connections: IntGauge,
+    pokeapi_requests: IntCounterVec,
+    pokeapi_duration: HistogramVec,
+    websocket_connections: IntGaugeVec
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, String> {
+        let metrics = Metrics {
+            registry: Registry::new(),
+            requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "Requests handled, by route and status"),
+                &["method", "route", "status"]
+            ).map_err(|e| e.to_string())?,
+            request_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "Time spent handling requests"),
+                &["method", "route"]
+            ).map_err(|e| e.to_string())?,
+            request_errors: IntCounterVec::new(
+                Opts::new("http_request_errors_total", "Requests answered with a 4xx or 5xx status"),
+                &["method", "route", "status"]
+            ).map_err(|e| e.to_string())?,
+            pool_connections: IntGauge::new("db_pool_connections", "Open Postgres connections")
+                .map_err(|e| e.to_string())?,
+            pool_idle_connections: IntGauge::new("db_pool_idle_connections", "Idle Postgres connections")
+                .map_err(|e| e.to_string())?,
+            pool_max_connections: IntGauge::new("db_pool_max_connections", "Postgres pool size limit")
+                .map_err(|e| e.to_string())?,
+            pokeapi_requests: IntCounterVec::new(
+                Opts::new("pokeapi_requests_total", "Calls made to PokeAPI, by outcome"),
+                &["outcome"]
+            ).map_err(|e| e.to_string())?,
+            pokeapi_duration: HistogramVec::new(
+                HistogramOpts::new("pokeapi_request_duration_seconds", "Time spent waiting on PokeAPI"),
+                &["outcome"]
+            ).map_err(|e| e.to_string())?,
+            websocket_connections: IntGaugeVec::new(
+                Opts::new("websocket_connections", "Open WebSocket connections"),
+                &["kind"]
+            ).map_err(|e| e.to_string())?
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.requests.clone()),
+            Box::new(metrics.request_duration.clone()),
+            Box::new(metrics.request_errors.clone()),
+            Box::new(metrics.pool_connections.clone()),
+            Box::new(metrics.pool_idle_connections.clone()),
+            Box::new(metrics.pool_max_connections.clone()),
+            Box::new(metrics.pokeapi_requests.clone()),
+            Box::new(metrics.pokeapi_duration.clone()),
+            Box::new(metrics.websocket_connections.clone())
+        ];
+        for collector in collectors {
+            if let Err(e) = metrics.registry.register(collector) {
+                return Err(format!("could not register metric: {}", e));
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    pub fn observe_pokeapi(&self, outcome: &str, started: Instant) {
+        self.pokeapi_requests.with_label_values(&[outcome]).inc();
+        self.pokeapi_duration.with_label_values(&[outcome]).observe(started.elapsed().as_secs_f64());
+    }
+
+    /// Counts a WebSocket as open until the returned guard is dropped.
+    pub fn websocket_opened(&self, kind: &str) -> ConnectionGuard {
+        let gauge = self.websocket_connections.with_label_values(&[kind]);
+        gauge.inc();
+
+        ConnectionGuard { gauge }
+    }
+
+    /// Text exposition of every metric, with the pool gauges read just now.
+    pub fn render(&self, pool: &PgPool) -> Result<String, String> {
+        self.pool_connections.set(pool.size() as i64);
+        self.pool_idle_connections.set(pool.num_idle() as i64);
+        self.pool_max_connections.set(pool.options().get_max_connections() as i64);
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            return Err(format!("could not encode metrics: {}", e));
+        }
+
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+}
+
+pub struct ConnectionGuard {
+    gauge: IntGauge
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
+
+pub async fn track_requests<B>(
+    State(store): State<MetricsStore>,
+    request: Request<B>,
+    next: Next<B>
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    // label by route template, raw paths would give every /1/... its own series
+    let route = request.extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    let status_label = status.as_u16().to_string();
+    let metrics = &store.metrics;
+
+    metrics.requests.with_label_values(&[&method, &route, &status_label]).inc();
+    metrics.request_duration.with_label_values(&[&method, &route]).observe(started.elapsed().as_secs_f64());
+    if status.is_client_error() || status.is_server_error() {
+        metrics.request_errors.with_label_values(&[&method, &route, &status_label]).inc();
+    }
+
+    response
+}
@@ -0,0 +1,158 @@
+// Just enough of the S2 geometry library to turn a cell id into the
+// coordinates of the cell centre. Names follow the reference implementation
+// (s2cell_id.h / s2coords.h) so the two can be compared side by side.
+
+const MAX_LEVEL: u32 = 30;
+const MAX_SI_TI: f64 = (1u64 << (MAX_LEVEL + 1)) as f64;
+
+const SWAP_MASK: u8 = 0x01;
+const INVERT_MASK: u8 = 0x02;
+
+// Hilbert curve position (0..4) to the (i, j) quadrant it covers, packed as
+// (i << 1) | j, for each of the four curve orientations.
+const POS_TO_IJ: [[u8; 4]; 4] = [
+    [0, 1, 3, 2],
+    [0, 2, 3, 1],
+    [3, 2, 0, 1],
+    [3, 1, 0, 2]
+];
+
+const POS_TO_ORIENTATION: [u8; 4] = [SWAP_MASK, 0, 0, INVERT_MASK | SWAP_MASK];
+
+pub fn is_valid_cell(id: u64) -> bool {
+    let face = id >> 61;
+    let lsb = id & id.wrapping_neg();
+
+    face < 6 && lsb & 0x1555555555555555 != 0
+}
+
+fn to_face_ij(id: u64) -> (u8, u32, u32) {
+    let face = (id >> 61) as u8;
+    let mut orientation = face & SWAP_MASK;
+    let mut i: u32 = 0;
+    let mut j: u32 = 0;
+
+    for level in 1..=MAX_LEVEL {
+        let shift = 2 * (MAX_LEVEL - level) + 1;
+        let position = ((id >> shift) & 3) as usize;
+        let ij = POS_TO_IJ[orientation as usize][position];
+
+        i = (i << 1) | (ij >> 1) as u32;
+        j = (j << 1) | (ij & 1) as u32;
+        orientation ^= POS_TO_ORIENTATION[position];
+    }
+
+    (face, i, j)
+}
+
+fn center_si_ti(id: u64) -> (u8, u64, u64) {
+    let (face, i, j) = to_face_ij(id);
+
+    // the decoded (i, j) is a leaf inside the cell, nudging it by `delta`
+    // leaf half-widths lands on the centre of the cell itself
+    let delta = if id & 1 == 1 {
+        1
+    } else if (i as u64 ^ (id >> 2)) & 1 == 1 {
+        2
+    } else {
+        0
+    };
+
+    (face, 2 * i as u64 + delta, 2 * j as u64 + delta)
+}
+
+fn st_to_uv(s: f64) -> f64 {
+    if s >= 0.5 {
+        (1.0 / 3.0) * (4.0 * s * s - 1.0)
+    } else {
+        (1.0 / 3.0) * (1.0 - 4.0 * (1.0 - s) * (1.0 - s))
+    }
+}
+
+fn face_uv_to_xyz(face: u8, u: f64, v: f64) -> (f64, f64, f64) {
+    match face {
+        0 => (1.0, u, v),
+        1 => (-u, 1.0, v),
+        2 => (-u, -v, 1.0),
+        3 => (-1.0, -v, -u),
+        4 => (v, -1.0, -u),
+        _ => (v, u, -1.0)
+    }
+}
+
+/// Latitude and longitude, in degrees, of the centre of an S2 cell.
+pub fn cell_center(id: u64) -> (f64, f64) {
+    let (face, si, ti) = center_si_ti(id);
+
+    let u = st_to_uv(si as f64 / MAX_SI_TI);
+    let v = st_to_uv(ti as f64 / MAX_SI_TI);
+    let (x, y, z) = face_uv_to_xyz(face, u, v);
+
+    let lat = z.atan2((x * x + y * y).sqrt());
+    let lng = y.atan2(x);
+
+    (lat.to_degrees(), lng.to_degrees())
+}
+
+fn format_angle(degrees: f64, positive: char, negative: char) -> String {
+    let hemisphere = if degrees < 0.0 { negative } else { positive };
+
+    // rounding once on the whole value carries 59.9996'' over into the
+    // minutes instead of printing 60.000''
+    let total_millis = (degrees.abs() * 3_600_000.0).round() as u64;
+    let whole_degrees = total_millis / 3_600_000;
+    let minutes = total_millis % 3_600_000 / 60_000;
+    let seconds = (total_millis % 60_000) as f64 / 1000.0;
+
+    format!("{}°{}'{:.3}''{}", whole_degrees, minutes, seconds, hemisphere)
+}
+
+/// Formats coordinates as `83°39'54.324''N 30°37'40.584''W`.
+pub fn format_dms(lat: f64, lng: f64) -> String {
+    format!("{} {}", format_angle(lat, 'N', 'S'), format_angle(lng, 'E', 'W'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(binary: &str) -> u64 {
+        u64::from_str_radix(binary, 2).unwrap()
+    }
+
+    #[test]
+    fn converts_challenge_examples() {
+        let (lat, lng) = cell_center(parse("0100111110010011000110011001010101011111000010100011110001011011"));
+        assert_eq!(format_dms(lat, lng), "83°39'54.324''N 30°37'40.584''W");
+
+        let (lat, lng) = cell_center(parse("0010000111110000011111100000111010111100000100111101111011000101"));
+        assert_eq!(format_dms(lat, lng), "18°54'55.944''S 47°31'17.976''E");
+    }
+
+    #[test]
+    fn face_centres() {
+        let expected: [(f64, f64); 6] = [(0.0, 0.0), (0.0, 90.0), (90.0, 0.0), (0.0, 180.0), (0.0, -90.0), (-90.0, 0.0)];
+
+        for (face, &(expected_lat, expected_lng)) in expected.iter().enumerate() {
+            let (lat, lng) = cell_center(((face as u64) << 61) | (1 << 60));
+            assert!((lat - expected_lat).abs() < 1e-9, "face {} lat {}", face, lat);
+            if expected_lat.abs() < 90.0 {
+                assert!((lng.abs() - expected_lng.abs()).abs() < 1e-9, "face {} lng {}", face, lng);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_cells() {
+        assert!(!is_valid_cell(0));
+        assert!(!is_valid_cell(7 << 61 | 1));
+        assert!(!is_valid_cell(1 << 1));
+        assert!(is_valid_cell(1 << 60));
+        assert!(is_valid_cell(parse("0100111110010011000110011001010101011111000010100011110001011011")));
+    }
+
+    #[test]
+    fn carries_rounded_seconds() {
+        assert_eq!(format_dms(10.0 - 1e-8, -0.5), "10°0'0.000''N 0°30'0.000''W");
+    }
+}
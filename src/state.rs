@@ -1,6 +1,12 @@
-use std::sync::Arc;
+use std::{collections::{HashMap, HashSet}, sync::{atomic::AtomicU64, Arc}};
+use axum::extract::FromRef;
 use sqlx::PgPool;
-use tokio::{sync::Mutex, time::Instant};
+use tokio::{sync::{broadcast, Mutex}, time::Instant};
+use ulid::{Generator, Ulid};
+
+use crate::{config::AppConfig, geo::CountryIndex, metrics::Metrics, structs::PasswordPolicy};
+
+pub use crate::structs::ChatMessage;
 
 #[derive(Clone)]
 pub struct IdStore {
@@ -15,7 +21,7 @@ pub struct PacketId {
 impl PacketId {
     pub fn new(packet_id: String) -> Self {
         PacketId {
-            packet_id: packet_id,
+            packet_id,
             timestamp: Instant::now()
         }
     }
@@ -24,4 +30,156 @@ impl PacketId {
 #[derive(Clone)]
 pub struct PgState {
     pub pool: PgPool
-}
\ No newline at end of file
+}
+
+#[derive(Clone)]
+pub struct PolicyStore {
+    pub policies: Arc<HashMap<String, PasswordPolicy>>
+}
+
+#[derive(Clone)]
+pub struct BreachStore {
+    pub hashes: Arc<HashSet<String>>
+}
+
+#[derive(Clone, Default)]
+pub struct ChatState {
+    pub rooms: Arc<Mutex<HashMap<String, broadcast::Sender<ChatMessage>>>>,
+    pub views: Arc<AtomicU64>,
+    pub replay_window: usize,
+    pub ids: Arc<Mutex<Generator>>
+}
+
+impl ChatState {
+    pub fn new(replay_window: usize) -> Self {
+        ChatState {
+            replay_window,
+            ..Default::default()
+        }
+    }
+
+    pub async fn next_id(&self) -> Ulid {
+        match self.ids.lock().await.generate() {
+            Ok(id) => id,
+            Err(_) => Ulid::new()
+        }
+    }
+
+    /// Subscribes while the map is locked, so a concurrent `leave` can't
+    /// drop the room between the lookup and the subscription.
+    pub async fn join(&self, room: &str) -> (broadcast::Sender<ChatMessage>, broadcast::Receiver<ChatMessage>) {
+        let mut rooms = self.rooms.lock().await;
+
+        let sender = rooms.entry(room.to_string())
+            .or_insert_with(|| broadcast::channel(100).0);
+
+        (sender.clone(), sender.subscribe())
+    }
+
+    /// Removes the room once nobody is subscribed, so receivers must be
+    /// dropped before calling this.
+    pub async fn leave(&self, room: &str) {
+        let mut rooms = self.rooms.lock().await;
+
+        if let Some(sender) = rooms.get(room) {
+            if sender.receiver_count() == 0 {
+                rooms.remove(room);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CountryStore {
+    pub index: Arc<CountryIndex>
+}
+
+#[derive(Clone)]
+pub struct ConfigStore {
+    pub config: Arc<AppConfig>
+}
+
+#[derive(Clone)]
+pub struct MetricsStore {
+    pub metrics: Arc<Metrics>
+}
+
+#[derive(Clone)]
+pub struct PokeApiState {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub metrics: Arc<Metrics>
+}
+
+impl PokeApiState {
+    pub fn new(config: &AppConfig, metrics: Arc<Metrics>) -> Result<Self, String> {
+        match reqwest::Client::builder().timeout(config.upstream_timeout()).build() {
+            Ok(client) => Ok(PokeApiState {
+                client,
+                base_url: config.pokeapi_url.trim_end_matches('/').to_string(),
+                metrics
+            }),
+            Err(e) => Err(format!("could not build http client: {}", e))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pg: PgState,
+    pub policies: PolicyStore,
+    pub breached: BreachStore,
+    pub chat: ChatState,
+    pub countries: CountryStore,
+    pub config: ConfigStore,
+    pub pokeapi: PokeApiState,
+    pub metrics: MetricsStore
+}
+
+impl FromRef<AppState> for PgState {
+    fn from_ref(state: &AppState) -> Self {
+        state.pg.clone()
+    }
+}
+
+impl FromRef<AppState> for PolicyStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.policies.clone()
+    }
+}
+
+impl FromRef<AppState> for BreachStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.breached.clone()
+    }
+}
+
+impl FromRef<AppState> for ChatState {
+    fn from_ref(state: &AppState) -> Self {
+        state.chat.clone()
+    }
+}
+
+impl FromRef<AppState> for CountryStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.countries.clone()
+    }
+}
+
+impl FromRef<AppState> for ConfigStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for PokeApiState {
+    fn from_ref(state: &AppState) -> Self {
+        state.pokeapi.clone()
+    }
+}
+
+impl FromRef<AppState> for MetricsStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
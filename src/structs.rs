@@ -1,13 +1,84 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use aho_corasick::AhoCorasick;
+
+use chrono::{DateTime, Utc};
+use num_bigint::BigInt;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use ulid::Ulid;
+
+use crate::utils::{
+    character_pool,
+    find_dictionary_words,
+    find_keyboard_runs,
+    find_repeats,
+    find_sequences,
+    password_hashes,
+    COMMON_WORDS
+};
 
 #[derive(Deserialize)]
 pub struct Reindeer {
     pub name: String,
-    pub strength: u64
+    pub strength: u64,
+    #[serde(default)]
+    pub weight: Option<u64>
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StrengthMode {
+    #[default]
+    Sum,
+    Grouped,
+    Weighted
+}
+
+#[derive(Debug, Serialize)]
+pub struct StrengthSummary {
+    pub mode: StrengthMode,
+    pub count: usize,
+    pub total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, u64>>
+}
+
+impl StrengthSummary {
+    pub fn compute(reindeers: &[Reindeer], mode: StrengthMode) -> Result<Self, String> {
+        let mut total: u64 = 0;
+        let mut groups: BTreeMap<String, u64> = BTreeMap::new();
+
+        for reindeer in reindeers {
+            let strength = match mode {
+                StrengthMode::Weighted => match reindeer.strength.checked_mul(reindeer.weight.unwrap_or(1)) {
+                    Some(strength) => strength,
+                    None => return Err(format!("weighted strength of {} overflows", reindeer.name))
+                },
+                _ => reindeer.strength
+            };
+
+            total = match total.checked_add(strength) {
+                Some(total) => total,
+                None => return Err("total strength overflows".to_string())
+            };
+
+            if mode == StrengthMode::Grouped {
+                // cannot overflow when the overall total did not
+                *groups.entry(reindeer.name.clone()).or_insert(0) += strength;
+            }
+        }
+
+        Ok(StrengthSummary {
+            mode,
+            count: reindeers.len(),
+            total,
+            groups: if mode == StrengthMode::Grouped { Some(groups) } else { None }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ReindeerContest {
     pub name: String,
     pub strength: u64,
@@ -30,12 +101,14 @@ pub struct ContestResult {
 
 impl ContestResult {
     pub fn get_result(reindeers: Vec<ReindeerContest>) -> Option<Self> {
-        let fastest= reindeers.iter().max_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap())?;
+        let fastest = reindeers.iter()
+            .filter(|reindeer| !reindeer.speed.is_nan())
+            .max_by(|a, b| a.speed.total_cmp(&b.speed))?;
         let tallest = reindeers.iter().max_by_key(|reindeer| reindeer.height)?;
         let magician = reindeers.iter().max_by_key(|reindeer| reindeer.snow_magic_power)?;
         let consumer = reindeers.iter().max_by_key(|reindeer| reindeer.candies_eaten_yesterday)?;
 
-        return Some(ContestResult {
+        Some(ContestResult {
             fastest: format!("Speeding past the finish line with a strength of {} is {}", fastest.strength, fastest.name),
             tallest: format!("{} is standing tall with his {} cm wide antlers", tallest.name, tallest.antler_width),
             magician: format!("{} could blast you away with a snow magic power of {}", magician.name, magician.snow_magic_power),
@@ -44,21 +117,174 @@ impl ContestResult {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Recipe {
-    pub flour: u64,
-    pub sugar: u64,
-    pub butter: u64,
-    #[serde(rename = "baking powder")]
-    pub baking_powder: u64,
-    #[serde(rename = "chocolate chips")]
-    pub chocolate_chips: u64
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ContestRanks {
+    pub fastest: usize,
+    pub tallest: usize,
+    pub magician: usize,
+    pub consumer: usize
+}
+
+impl ContestRanks {
+    pub fn titles(&self) -> usize {
+        [self.fastest, self.tallest, self.magician, self.consumer]
+            .iter()
+            .filter(|rank| **rank == 1)
+            .count()
+    }
+
+    pub fn average(&self) -> f64 {
+        (self.fastest + self.tallest + self.magician + self.consumer) as f64 / 4.0
+    }
+}
+
+impl ContestResult {
+    /// Standard competition ranking ("1224") for each category the contest
+    /// awards, NaN speeds ranking last.
+    pub fn rankings(reindeers: &[ReindeerContest]) -> Vec<(String, ContestRanks)> {
+        let rank = |value: f64, values: &[f64]| -> usize {
+            if value.is_nan() {
+                return values.len();
+            }
+            1 + values.iter().filter(|other| **other > value).count()
+        };
+
+        let speeds: Vec<f64> = reindeers.iter().map(|r| r.speed).collect();
+        let heights: Vec<f64> = reindeers.iter().map(|r| r.height as f64).collect();
+        let magic: Vec<f64> = reindeers.iter().map(|r| r.snow_magic_power as f64).collect();
+        let candies: Vec<f64> = reindeers.iter().map(|r| r.candies_eaten_yesterday as f64).collect();
+
+        reindeers.iter().map(|reindeer| {
+            (reindeer.name.clone(), ContestRanks {
+                fastest: rank(reindeer.speed, &speeds),
+                tallest: rank(reindeer.height as f64, &heights),
+                magician: rank(reindeer.snow_magic_power as f64, &magic),
+                consumer: rank(reindeer.candies_eaten_yesterday as f64, &candies)
+            })
+        }).collect()
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct ReindeerRow {
+    pub name: String,
+    pub strength: i64,
+    pub speed: f64,
+    pub height: i64,
+    pub antler_width: i64,
+    pub snow_magic_power: i64,
+    pub favorite_food: String,
+    pub candies_eaten_yesterday: i64
+}
+
+impl From<ReindeerRow> for ReindeerContest {
+    fn from(row: ReindeerRow) -> Self {
+        ReindeerContest {
+            name: row.name,
+            strength: row.strength as u64,
+            speed: row.speed,
+            height: row.height as u64,
+            antler_width: row.antler_width as u64,
+            snow_magic_power: row.snow_magic_power as u64,
+            favorite_food: row.favorite_food,
+            candies_eaten_yesterday: row.candies_eaten_yesterday as u64
+        }
+    }
+}
+
+impl TryFrom<ReindeerContest> for ReindeerRow {
+    type Error = String;
+
+    fn try_from(reindeer: ReindeerContest) -> Result<Self, Self::Error> {
+        let to_i64 = |value: u64, field: &str| -> Result<i64, String> {
+            i64::try_from(value).map_err(|_| format!("{} is too large to store", field))
+        };
+
+        Ok(ReindeerRow {
+            strength: to_i64(reindeer.strength, "strength")?,
+            speed: reindeer.speed,
+            height: to_i64(reindeer.height, "height")?,
+            antler_width: to_i64(reindeer.antler_width, "antler_width")?,
+            snow_magic_power: to_i64(reindeer.snow_magic_power, "snow_magic_power")?,
+            candies_eaten_yesterday: to_i64(reindeer.candies_eaten_yesterday, "candies_eaten_yesterday")?,
+            name: reindeer.name,
+            favorite_food: reindeer.favorite_food
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct ContestRow {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub roster: sqlx::types::Json<Vec<ReindeerContest>>
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct FullRecipe {
-    pub recipe: Recipe,
-    pub pantry: Recipe
+pub const CONTEST_ATTRIBUTES: [(&str, &str); 6] = [
+    ("strength", "strength"),
+    ("speed", "speed"),
+    ("height", "height"),
+    ("antler_width", "antler_width"),
+    ("snow_magic_power", "snow_magic_power"),
+    ("candies_eaten_yesterday", "cAnD13s_3ATeN-yesT3rdAy")
+];
+
+#[derive(Debug, Serialize)]
+pub struct AttributeRecord {
+    pub value: f64,
+    pub reindeer: Vec<String>
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttributeStats {
+    pub count: usize,
+    pub missing: Vec<String>,
+    pub min: Option<AttributeRecord>,
+    pub max: Option<AttributeRecord>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>
+}
+
+impl AttributeStats {
+    pub fn compute(values: &[(String, Option<f64>)]) -> Self {
+        let mut present: Vec<(&str, f64)> = Vec::new();
+        let mut missing: Vec<String> = Vec::new();
+
+        for (name, value) in values {
+            match value {
+                Some(value) if value.is_finite() => present.push((name, *value)),
+                _ => missing.push(name.clone())
+            }
+        }
+
+        present.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let record = |value: f64| AttributeRecord {
+            value,
+            reindeer: present.iter()
+                .filter(|(_, other)| *other == value)
+                .map(|(name, _)| name.to_string())
+                .collect()
+        };
+
+        let min = present.first().map(|(_, value)| record(*value));
+        let max = present.last().map(|(_, value)| record(*value));
+
+        let count = present.len();
+        let (mean, median) = if count == 0 {
+            (None, None)
+        } else {
+            let sum: f64 = present.iter().map(|(_, value)| value).sum();
+            let median = if count % 2 == 1 {
+                present[count / 2].1
+            } else {
+                (present[count / 2 - 1].1 + present[count / 2].1) / 2.0
+            };
+            (Some(sum / count as f64), Some(median))
+        };
+
+        AttributeStats { count, missing, min, max, mean, median }
+    }
 }
 
 #[derive(Serialize)]
@@ -146,4 +372,581 @@ impl RenderContent {
 #[derive(Debug, Deserialize)]
 pub struct Password{
     pub input: String
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatternRequest {
+    pub text: String,
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub case_insensitive: bool
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMatch {
+    pub pattern: String,
+    pub start: usize,
+    pub end: usize
+}
+
+pub const MAX_NGRAM: usize = 5;
+const MAX_POSITIONS: usize = 1000;
+
+#[derive(Debug, Serialize)]
+pub struct Frequency {
+    pub term: String,
+    pub count: u64
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatternStats {
+    pub count: u64,
+    pub positions: Vec<usize>
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextReport {
+    pub bytes: usize,
+    pub characters: u64,
+    pub lines: u64,
+    pub words: u64,
+    pub unique_words: usize,
+    pub top_words: Vec<Frequency>,
+    pub ngram: usize,
+    pub top_ngrams: Vec<Frequency>,
+    pub patterns: BTreeMap<String, PatternStats>
+}
+
+/// Running counts over text that arrives in pieces. Words are runs of
+/// alphanumerics and apostrophes, lowercased before counting.
+pub struct TextStats {
+    bytes: usize,
+    characters: u64,
+    newlines: u64,
+    last_char: Option<char>,
+    words: u64,
+    word: String,
+    frequencies: HashMap<String, u64>,
+    ngram: usize,
+    window: VecDeque<String>,
+    ngrams: HashMap<String, u64>,
+    matcher: Option<AhoCorasick>,
+    patterns: Vec<String>,
+    pattern_stats: Vec<PatternStats>,
+    longest_pattern: usize,
+    // tail of the previous chunk, so matches straddling a boundary are found
+    carry: String
+}
+
+impl TextStats {
+    pub fn new(ngram: usize, patterns: Vec<String>, matcher: Option<AhoCorasick>) -> Self {
+        TextStats {
+            bytes: 0,
+            characters: 0,
+            newlines: 0,
+            last_char: None,
+            words: 0,
+            word: String::new(),
+            frequencies: HashMap::new(),
+            ngram,
+            window: VecDeque::new(),
+            ngrams: HashMap::new(),
+            matcher,
+            longest_pattern: patterns.iter().map(|pattern| pattern.len()).max().unwrap_or(0),
+            pattern_stats: patterns.iter().map(|_| PatternStats { count: 0, positions: Vec::new() }).collect(),
+            patterns,
+            carry: String::new()
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            self.characters += 1;
+            if c == '\n' {
+                self.newlines += 1;
+            }
+
+            if c.is_alphanumeric() || c == '\'' {
+                self.word.extend(c.to_lowercase());
+            } else {
+                self.end_word();
+            }
+        }
+        if let Some(c) = chunk.chars().last() {
+            self.last_char = Some(c);
+        }
+
+        self.find_patterns(chunk);
+        self.bytes += chunk.len();
+    }
+
+    pub fn finish(&mut self) {
+        self.end_word();
+    }
+
+    fn end_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+
+        let word = std::mem::take(&mut self.word);
+        self.words += 1;
+        *self.frequencies.entry(word.clone()).or_insert(0) += 1;
+
+        self.window.push_back(word);
+        if self.window.len() > self.ngram {
+            self.window.pop_front();
+        }
+        if self.window.len() == self.ngram {
+            let key = self.window.iter().map(String::as_str).collect::<Vec<_>>().join(" ");
+            *self.ngrams.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    fn find_patterns(&mut self, chunk: &str) {
+        let matcher = match &self.matcher {
+            Some(matcher) => matcher,
+            None => return
+        };
+
+        let carry_len = self.carry.len();
+        let base = self.bytes - carry_len;
+        let text = std::mem::take(&mut self.carry) + chunk;
+
+        for found in matcher.find_overlapping_iter(&text) {
+            // anything ending inside the carry was counted with the last chunk
+            if found.end() <= carry_len {
+                continue;
+            }
+
+            let stats = &mut self.pattern_stats[found.pattern().as_usize()];
+            stats.count += 1;
+            if stats.positions.len() < MAX_POSITIONS {
+                stats.positions.push(base + found.start());
+            }
+        }
+
+        let mut keep_from = text.len().saturating_sub(self.longest_pattern.saturating_sub(1));
+        while !text.is_char_boundary(keep_from) {
+            keep_from += 1;
+        }
+        self.carry = text[keep_from..].to_string();
+    }
+
+    pub fn report(self, top: usize) -> TextReport {
+        let lines = match self.last_char {
+            Some('\n') | None => self.newlines,
+            Some(_) => self.newlines + 1
+        };
+
+        let mut patterns = BTreeMap::new();
+        for (pattern, stats) in self.patterns.into_iter().zip(self.pattern_stats) {
+            patterns.insert(pattern, stats);
+        }
+
+        TextReport {
+            bytes: self.bytes,
+            characters: self.characters,
+            lines,
+            words: self.words,
+            unique_words: self.frequencies.len(),
+            top_words: most_frequent(self.frequencies, top),
+            ngram: self.ngram,
+            top_ngrams: most_frequent(self.ngrams, top),
+            patterns
+        }
+    }
+}
+
+fn most_frequent(counts: HashMap<String, u64>, top: usize) -> Vec<Frequency> {
+    let mut frequencies: Vec<Frequency> = counts.into_iter()
+        .map(|(term, count)| Frequency { term, count })
+        .collect();
+
+    frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    frequencies.truncate(top);
+
+    frequencies
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordBatch {
+    pub inputs: Vec<String>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub vowels: String,
+    pub min_vowels: usize,
+    pub forbidden: Vec<String>,
+    pub repeat_window: usize,
+    pub case_sensitive: bool
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            vowels: "aeiouy".to_string(),
+            min_vowels: 3,
+            forbidden: vec!["ab".to_string(), "cd".to_string(), "pq".to_string(), "xy".to_string()],
+            repeat_window: 2,
+            case_sensitive: true
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn is_nice(&self, input: &str) -> bool {
+        let (input, vowels, forbidden) = if self.case_sensitive {
+            (input.to_string(), self.vowels.clone(), self.forbidden.clone())
+        } else {
+            (
+                input.to_lowercase(),
+                self.vowels.to_lowercase(),
+                self.forbidden.iter().map(|s| s.to_lowercase()).collect()
+            )
+        };
+
+        let has_enough_vowels = input.chars()
+            .filter(|c| vowels.contains(*c))
+            .count() >= self.min_vowels;
+
+        // a window of 2 means the same character twice in a row, 3 allows one
+        // character in between, and so on. 0 or 1 disables the rule.
+        let chars: Vec<char> = input.chars().collect();
+        let has_repeated_letter = self.repeat_window < 2 || (0..chars.len()).any(|i| {
+            let end = std::cmp::min(i + self.repeat_window, chars.len());
+            chars[i + 1..end].contains(&chars[i])
+        });
+
+        let no_forbidden_substrings = !forbidden
+            .iter()
+            .any(|s| input.contains(s.as_str()));
+
+        has_enough_vowels && has_repeated_letter && no_forbidden_substrings
+    }
+}
+
+/// Longest password `/15/strength` will score, in characters.
+pub const MAX_PASSWORD_LENGTH: usize = 256;
+
+#[derive(Debug, Serialize)]
+pub struct StrengthReport {
+    pub score: u8,
+    pub entropy: f64,
+    pub breached: bool,
+    pub patterns: Vec<String>,
+    pub feedback: Vec<String>
+}
+
+impl StrengthReport {
+    pub fn evaluate(input: &str, breached_hashes: &HashSet<String>) -> Self {
+        let mut patterns: Vec<String> = Vec::new();
+        let mut feedback: Vec<String> = Vec::new();
+
+        let pool = character_pool(input);
+        let bits_per_char = if pool > 0 { (pool as f64).log2() } else { 0.0 };
+        let length = input.chars().count();
+        let mut entropy = length as f64 * bits_per_char;
+
+        // every predictable character beyond the first one in a pattern adds
+        // next to nothing for an attacker, so it is removed from the estimate
+        for run in find_keyboard_runs(input) {
+            entropy -= (run.chars().count() - 1) as f64 * bits_per_char;
+            patterns.push(format!("keyboard pattern \"{}\"", run));
+        }
+        if !patterns.is_empty() {
+            feedback.push("Avoid runs of neighbouring keys like \"qwerty\" or \"1234\".".to_string());
+        }
+
+        let sequences = find_sequences(input);
+        for sequence in &sequences {
+            entropy -= (sequence.chars().count() - 1) as f64 * bits_per_char;
+            patterns.push(format!("sequence \"{}\"", sequence));
+        }
+        if !sequences.is_empty() {
+            feedback.push("Avoid alphabetical or numerical sequences like \"abc\" or \"789\".".to_string());
+        }
+
+        let repeats = find_repeats(input);
+        for repeat in &repeats {
+            entropy -= (repeat.chars().count() / 2) as f64 * bits_per_char;
+            patterns.push(format!("repeated \"{}\"", repeat));
+        }
+        if !repeats.is_empty() {
+            feedback.push("Avoid repeating characters or chunks of the password.".to_string());
+        }
+
+        let words = find_dictionary_words(input);
+        for word in &words {
+            entropy -= word.len() as f64 * bits_per_char - (COMMON_WORDS.len() as f64).log2();
+            patterns.push(format!("dictionary word \"{}\"", word));
+        }
+        if !words.is_empty() {
+            feedback.push("Common words are guessed first, even with letters swapped for symbols.".to_string());
+        }
+
+        let (sha1_hash, sha256_hash) = password_hashes(input);
+        let breached = breached_hashes.contains(&sha1_hash) || breached_hashes.contains(&sha256_hash);
+        if breached {
+            feedback.push("This password appears in a breached password list, never use it.".to_string());
+        }
+
+        if length < 12 {
+            feedback.push("Use at least 12 characters.".to_string());
+        }
+        if pool < 62 {
+            feedback.push("Mix lowercase, uppercase, digits and symbols.".to_string());
+        }
+
+        let entropy = entropy.max(0.0);
+
+        let score = if breached {
+            0
+        } else {
+            match entropy {
+                e if e < 28.0 => 0,
+                e if e < 36.0 => 1,
+                e if e < 60.0 => 2,
+                e if e < 80.0 => 3,
+                _ => 4
+            }
+        };
+
+        StrengthReport {
+            score,
+            entropy: (entropy * 100.0).round() / 100.0,
+            breached,
+            patterns,
+            feedback
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PingPongGame {
+    pub started: bool,
+    pub pings: u64
+}
+
+impl PingPongGame {
+    pub fn play(&mut self, text: &str) -> Option<&'static str> {
+        match text {
+            "serve" => {
+                self.started = true;
+                None
+            },
+            "ping" if self.started => {
+                self.pings += 1;
+                Some("pong")
+            },
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncomingChat {
+    pub message: String
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatMessage {
+    // only used to tell live messages apart from replayed ones
+    #[serde(skip)]
+    pub id: Ulid,
+    pub user: String,
+    pub message: String
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StoredChatMessage {
+    pub id: String,
+    #[sqlx(rename = "username")]
+    pub user: String,
+    pub message: String
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ArchiveSummary {
+    pub files: u64,
+    pub total_size: u64
+}
+
+#[derive(Debug)]
+pub struct StarMap {
+    pub stars: Vec<[f64; 3]>,
+    pub portals: Vec<Vec<usize>>
+}
+
+impl StarMap {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut lines = input.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+        // counts come from the body, so bound them by the lines actually sent
+        let line_count = input.lines().count();
+
+        let star_count = parse_count(lines.next(), "star count", line_count.saturating_sub(1))?;
+        let mut stars: Vec<[f64; 3]> = Vec::new();
+
+        for _ in 0..star_count {
+            let (line_number, line) = match lines.next() {
+                Some(line) => line,
+                None => return Err(format!("expected {} stars but the input ended after {}", star_count, stars.len()))
+            };
+
+            let coordinates: Vec<&str> = line.split_whitespace().collect();
+            if coordinates.len() != 3 {
+                return Err(format!("line {}: expected 3 coordinates, found {}", line_number, coordinates.len()));
+            }
+
+            let mut star = [0.0; 3];
+            for (axis, coordinate) in coordinates.iter().enumerate() {
+                star[axis] = match coordinate.parse::<i64>() {
+                    Ok(value) => value as f64,
+                    Err(_) => return Err(format!("line {}: {:?} is not an integer coordinate", line_number, coordinate))
+                };
+            }
+            stars.push(star);
+        }
+
+        let portal_count = parse_count(lines.next(), "portal count", line_count.saturating_sub(star_count + 2))?;
+        let mut portals: Vec<Vec<usize>> = vec![Vec::new(); star_count];
+
+        for parsed in 0..portal_count {
+            let (line_number, line) = match lines.next() {
+                Some(line) => line,
+                None => return Err(format!("expected {} portals but the input ended after {}", portal_count, parsed))
+            };
+
+            let ends: Vec<&str> = line.split_whitespace().collect();
+            if ends.len() != 2 {
+                return Err(format!("line {}: expected 2 star indices, found {}", line_number, ends.len()));
+            }
+
+            let mut indices = [0usize; 2];
+            for (position, end) in ends.iter().enumerate() {
+                indices[position] = match end.parse::<usize>() {
+                    Ok(index) if index < star_count => index,
+                    _ => return Err(format!("line {}: {:?} is not a star index below {}", line_number, end, star_count))
+                };
+            }
+            portals[indices[0]].push(indices[1]);
+        }
+
+        if let Some((line_number, _)) = lines.find(|(_, line)| !line.is_empty()) {
+            return Err(format!("line {}: unexpected content after the last portal", line_number));
+        }
+
+        Ok(StarMap { stars, portals })
+    }
+
+    /// Fewest portals from the first star to the last one, and the distance
+    /// travelled along that route.
+    pub fn shortest_route(&self) -> Option<(usize, f64)> {
+        let target = self.stars.len().checked_sub(1)?;
+        let mut previous: Vec<Option<usize>> = vec![None; self.stars.len()];
+        let mut visited = vec![false; self.stars.len()];
+        let mut queue = VecDeque::from([0]);
+        visited[0] = true;
+
+        while let Some(star) = queue.pop_front() {
+            if star == target {
+                break;
+            }
+            for &next in &self.portals[star] {
+                if !visited[next] {
+                    visited[next] = true;
+                    previous[next] = Some(star);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        if !visited[target] {
+            return None;
+        }
+
+        let mut portals = 0;
+        let mut distance = 0.0;
+        let mut current = target;
+        while let Some(star) = previous[current] {
+            let [x1, y1, z1] = self.stars[star];
+            let [x2, y2, z2] = self.stars[current];
+            distance += ((x2 - x1).powi(2) + (y2 - y1).powi(2) + (z2 - z1).powi(2)).sqrt();
+            portals += 1;
+            current = star;
+        }
+
+        Some((portals, distance))
+    }
+}
+
+fn parse_count(line: Option<(usize, &str)>, what: &str, remaining: usize) -> Result<usize, String> {
+    match line {
+        Some((line_number, line)) => match line.parse::<usize>() {
+            Ok(count) if count > remaining => Err(format!(
+                "line {}: {} {} exceeds the {} lines that follow",
+                line_number, what, count, remaining
+            )),
+            Ok(count) => Ok(count),
+            Err(_) => Err(format!("line {}: expected the {}, found {:?}", line_number, what, line))
+        },
+        None => Err(format!("missing {}", what))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitOperation {
+    #[default]
+    Xor,
+    And,
+    Or,
+    Sum
+}
+
+#[derive(Debug, Serialize)]
+pub struct BitsBreakdown {
+    pub numbers: Vec<i64>,
+    pub operation: BitOperation,
+    pub steps: Vec<String>,
+    pub combined: String,
+    pub power: u32,
+    pub result: String
+}
+
+impl BitsBreakdown {
+    pub fn evaluate(numbers: Vec<i64>, operation: BitOperation, power: u32) -> Self {
+        // i128 holds the sum of 20 i64 values, and the bitwise operations
+        // never leave the i64 range
+        let mut accumulator: i128 = numbers.first().copied().unwrap_or(0) as i128;
+        let mut steps: Vec<String> = vec![accumulator.to_string()];
+
+        for &number in numbers.iter().skip(1) {
+            let number = number as i128;
+            accumulator = match operation {
+                BitOperation::Xor => accumulator ^ number,
+                BitOperation::And => accumulator & number,
+                BitOperation::Or => accumulator | number,
+                BitOperation::Sum => accumulator + number
+            };
+            steps.push(accumulator.to_string());
+        }
+
+        let result = BigInt::from(accumulator).pow(power);
+
+        BitsBreakdown {
+            numbers,
+            operation,
+            steps,
+            combined: accumulator.to_string(),
+            power,
+            result: result.to_string()
+        }
+    }
+}
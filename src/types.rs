@@ -1,10 +1,10 @@
 use std::{str::FromStr, fmt};
 
-use axum::{response::{Response, IntoResponse}, Json, http::StatusCode};
+use axum::{response::{Response, IntoResponse}, Json, http::{HeaderMap, StatusCode}};
 use serde::{Deserialize, Deserializer, de};
 use serde_json::Value;
 
-use crate::structs::UlidCalc;
+use crate::structs::{BitOperation, StrengthMode, UlidCalc};
 
 fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
 where
@@ -26,34 +26,151 @@ pub struct Pagination {
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub limit: Option<usize>,
     #[serde(default, deserialize_with = "empty_string_as_none")]
-    pub split: Option<usize>
+    pub split: Option<usize>,
+    #[serde(default)]
+    pub meta: Option<PageMeta>
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PageMeta {
+    Header,
+    Envelope
+}
+
+pub struct Page {
+    pub start: usize,
+    pub end: usize,
+    pub total: usize,
+    pub next_offset: Option<usize>,
+    pub pages: usize
+}
+
+impl Pagination {
+    pub fn page(&self, total: usize) -> Result<Page, String> {
+        let start = self.offset.unwrap_or(0);
+        if start > total {
+            return Err(format!("offset {} is past the end of a list of {}", start, total));
+        }
+
+        if self.split == Some(0) {
+            return Err("split must be greater than 0".to_string());
+        }
+
+        let limit = self.limit.unwrap_or(total - start);
+        let end = std::cmp::min(start.saturating_add(limit), total);
+
+        Ok(Page {
+            start,
+            end,
+            total,
+            next_offset: if end < total { Some(end) } else { None },
+            pages: if limit == 0 { 0 } else { total.div_ceil(limit) }
+        })
+    }
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    Plain,
+    Json
+}
+
+#[derive(Deserialize)]
+pub struct ElfQuery {
+    #[serde(default)]
+    pub case_insensitive: bool
+}
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub top: Option<usize>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub ngram: Option<usize>,
+    #[serde(default)]
+    pub patterns: Option<String>,
+    #[serde(default)]
+    pub case_insensitive: bool
+}
+
+#[derive(Deserialize)]
+pub struct StrengthQuery {
+    #[serde(default)]
+    pub mode: Option<StrengthMode>,
+    #[serde(default)]
+    pub format: Option<ResponseFormat>
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsMode {
+    Full,
+    Compat
+}
+
+#[derive(Deserialize)]
+pub struct AnalyticsQuery {
+    #[serde(default)]
+    pub mode: Option<AnalyticsMode>
+}
+
+#[derive(Deserialize)]
+pub struct BitsQuery {
+    #[serde(default)]
+    pub op: Option<BitOperation>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub pow: Option<u32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub explain: Option<bool>
+}
+
+#[derive(Deserialize)]
+pub struct ChatQuery {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub history: Option<usize>
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub before: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub limit: Option<usize>
 }
 
 pub enum ApiResponse {
     Ok,
+    NotFound,
     ServerError,
+    Unavailable(Value),
     RequestErrorAndJson(Value),
     JsonValue(Value),
-    Integer(i64),
+    JsonWithHeaders(Value, HeaderMap),
     Unsigned(u64),
     String(String),
     PngImage(Vec<u8>),
     Ulid(UlidCalc),
-    HtmlRaw(String)
+    HtmlRaw(String),
+    Metrics(String)
 }
 
 impl IntoResponse for ApiResponse {
     fn into_response(self) -> Response {
         match self {
             ApiResponse::Ok => (StatusCode::OK).into_response(),
+            ApiResponse::NotFound => (StatusCode::NOT_FOUND).into_response(),
             ApiResponse::ServerError => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            ApiResponse::Unavailable(data) => (StatusCode::SERVICE_UNAVAILABLE, Json(data)).into_response(),
             ApiResponse::JsonValue(data) => (StatusCode::OK, Json(data)).into_response(),
-            ApiResponse::Integer(number) => (StatusCode::OK, number.to_string()).into_response(),
+            ApiResponse::JsonWithHeaders(data, headers) => (StatusCode::OK, headers, Json(data)).into_response(),
             ApiResponse::Unsigned(number) => (StatusCode::OK, number.to_string()).into_response(),
             ApiResponse::String(string) => (StatusCode::OK, string.to_string()).into_response(),
             ApiResponse::PngImage(data) => (StatusCode::OK, [("Content-Type", "image/png")], data).into_response(),
             ApiResponse::Ulid(data) => (StatusCode::OK, Json(data)).into_response(),
             ApiResponse::HtmlRaw(data) => (StatusCode::OK, [("Content-Type", "text/html")], data).into_response(),
+            ApiResponse::Metrics(data) => (StatusCode::OK, [("Content-Type", "text/plain; version=0.0.4")], data).into_response(),
             ApiResponse::RequestErrorAndJson(data) => (StatusCode::BAD_REQUEST, Json(data)).into_response(),
 
         }
@@ -1,11 +1,21 @@
-use axum::http::HeaderMap;
+use std::{collections::{HashMap, HashSet}, io, path::Path};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use axum::{body::Bytes, extract::BodyStream, http::HeaderMap};
 use base64::{engine::general_purpose, Engine as _};
+use sha1::Sha1;
+use futures_util::{Stream, StreamExt};
+use git2::{BranchType, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tokio_tar::Archive;
+use tokio_util::io::StreamReader;
+
+use crate::structs::{ArchiveSummary, PasswordPolicy, PatternMatch, TextStats};
 
 pub fn extract_recipe(headers: HeaderMap) -> Option<String> {
-    let cookie_header = match headers.get("Cookie") {
-        Some(c) => c,
-        None => return None
-    };
+    let cookie_header = headers.get("Cookie")?;
 
     let cookie_str = match cookie_header.to_str() {
         Ok(str) => str,
@@ -36,3 +46,474 @@ pub fn extract_recipe(headers: HeaderMap) -> Option<String> {
 pub fn is_lsb_1(ulid_bytes: &[u8; 16]) -> bool {
     ulid_bytes[15] & 1 == 1
 }
+
+pub fn load_policies(path: &str) -> Result<HashMap<String, PasswordPolicy>, String> {
+    let mut policies: HashMap<String, PasswordPolicy> = HashMap::new();
+
+    if Path::new(path).exists() {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("could not read {}: {}", path, e))
+        };
+
+        policies = if path.ends_with(".json") {
+            match serde_json::from_str(&contents) {
+                Ok(policies) => policies,
+                Err(e) => return Err(format!("invalid policy file {}: {}", path, e))
+            }
+        } else {
+            match toml::from_str(&contents) {
+                Ok(policies) => policies,
+                Err(e) => return Err(format!("invalid policy file {}: {}", path, e))
+            }
+        };
+    }
+
+    policies.entry("default".to_string()).or_default();
+
+    Ok(policies)
+}
+
+pub const COMMON_WORDS: [&str; 40] = [
+    "password", "passwd", "admin", "welcome", "letmein", "login", "master",
+    "dragon", "monkey", "shadow", "sunshine", "princess", "football", "baseball",
+    "soccer", "hockey", "batman", "superman", "trustno", "iloveyou", "secret",
+    "christmas", "santa", "reindeer", "rudolph", "snow", "winter", "summer",
+    "cookie", "elf", "shelf", "freedom", "whatever", "qazwsx", "hello",
+    "charlie", "jordan", "michael", "jennifer", "access"
+];
+
+const KEYBOARD_ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+pub fn character_pool(input: &str) -> u32 {
+    let mut pool = 0;
+
+    if input.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if input.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if input.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if input.chars().any(|c| c.is_ascii_punctuation() || c == ' ') {
+        pool += 33;
+    }
+    if !input.is_ascii() {
+        pool += 100;
+    }
+
+    pool
+}
+
+fn find_runs<F>(input: &str, min_len: usize, follows: F) -> Vec<String>
+where
+    F: Fn(char, char) -> bool
+{
+    let chars: Vec<char> = input.to_lowercase().chars().collect();
+    let mut runs: Vec<String> = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = start + 1;
+        while end < chars.len() && follows(chars[end - 1], chars[end]) {
+            end += 1;
+        }
+        if end - start >= min_len {
+            runs.push(chars[start..end].iter().collect());
+        }
+        start = end;
+    }
+
+    runs
+}
+
+fn is_keyboard_neighbour(a: char, b: char) -> bool {
+    KEYBOARD_ROWS.iter().any(|row| {
+        match (row.find(a), row.find(b)) {
+            (Some(i), Some(j)) => i + 1 == j,
+            _ => false
+        }
+    })
+}
+
+fn is_next_in_sequence(a: char, b: char) -> bool {
+    let same_class = (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+        || (a.is_ascii_digit() && b.is_ascii_digit());
+
+    same_class && a as u32 + 1 == b as u32
+}
+
+pub fn find_keyboard_runs(input: &str) -> Vec<String> {
+    let mut runs = find_runs(input, 3, is_keyboard_neighbour);
+    runs.extend(find_runs(input, 3, |a, b| is_keyboard_neighbour(b, a)));
+    runs
+}
+
+pub fn find_sequences(input: &str) -> Vec<String> {
+    let mut sequences = find_runs(input, 3, is_next_in_sequence);
+    sequences.extend(find_runs(input, 3, |a, b| is_next_in_sequence(b, a)));
+    sequences
+}
+
+/// Longest chunk `find_repeats` looks for, which keeps the scan linear.
+pub const MAX_REPEAT_CHUNK: usize = 16;
+
+pub fn find_repeats(input: &str) -> Vec<String> {
+    let mut repeats = find_runs(input, 3, |a, b| a == b);
+
+    let chars: Vec<char> = input.chars().collect();
+    let n = chars.len();
+
+    // same[i]: length of the run of identical characters starting at i
+    let mut same = vec![0; n + 1];
+    for i in (0..n).rev() {
+        same[i] = if i + 1 < n && chars[i] == chars[i + 1] { same[i + 1] + 1 } else { 1 };
+    }
+
+    // matching[k][i]: how many characters from i on equal the one k places later,
+    // so a chunk of length k repeats right after i when matching[k][i] >= k
+    let matching: Vec<Vec<usize>> = (0..=MAX_REPEAT_CHUNK).map(|k| {
+        let mut run = vec![0; n + 1];
+        if k > 0 {
+            for i in (0..n.saturating_sub(k)).rev() {
+                run[i] = if chars[i] == chars[i + k] { run[i + 1] + 1 } else { 0 };
+            }
+        }
+        run
+    }).collect();
+
+    let mut i = 0;
+    while i < n {
+        let longest_chunk = std::cmp::min((n - i) / 2, MAX_REPEAT_CHUNK);
+        let repeated = (2..=longest_chunk).rev().find(|&k| same[i] < k && matching[k][i] >= k);
+
+        match repeated {
+            Some(k) => {
+                repeats.push(chars[i..i + 2 * k].iter().collect());
+                i += 2 * k;
+            },
+            None => i += 1
+        }
+    }
+
+    repeats
+}
+
+pub fn find_dictionary_words(input: &str) -> Vec<String> {
+    let normalized: String = input.to_lowercase().chars().map(|c| match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c
+    }).collect();
+
+    let found: Vec<&str> = COMMON_WORDS.iter()
+        .filter(|word| normalized.contains(*word))
+        .copied()
+        .collect();
+
+    // "elf" inside "shelf" should only be reported once
+    found.iter()
+        .filter(|word| !found.iter().any(|other| other != *word && other.contains(*word)))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+pub fn password_hashes(input: &str) -> (String, String) {
+    let sha1_hash = format!("{:x}", Sha1::digest(input.as_bytes()));
+    let sha256_hash = format!("{:x}", Sha256::digest(input.as_bytes()));
+
+    (sha1_hash, sha256_hash)
+}
+
+pub fn load_breached_passwords(path: &str) -> Result<HashSet<String>, String> {
+    let mut hashes: HashSet<String> = HashSet::new();
+
+    if !Path::new(path).exists() {
+        return Ok(hashes);
+    }
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("could not read {}: {}", path, e))
+    };
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // accepts hex SHA-1 or SHA-256 digests, optionally followed by a
+        // ":count" suffix as in the HIBP dumps, and plain passwords otherwise
+        let candidate = line.split(':').next().unwrap_or(line);
+        let is_digest = (candidate.len() == 40 || candidate.len() == 64)
+            && candidate.chars().all(|c| c.is_ascii_hexdigit());
+
+        if is_digest {
+            hashes.insert(candidate.to_lowercase());
+        } else {
+            let (sha1_hash, sha256_hash) = password_hashes(line);
+            hashes.insert(sha1_hash);
+            hashes.insert(sha256_hash);
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// Reads a streamed body, failing once more than `limit` bytes have arrived:
+/// `DefaultBodyLimit` only covers the extractors that buffer the body.
+fn body_reader(body: BodyStream, limit: usize) -> StreamReader<impl Stream<Item = io::Result<Bytes>> + Unpin, Bytes> {
+    let mut received: usize = 0;
+
+    StreamReader::new(body.map(move |chunk| match chunk {
+        Ok(bytes) => {
+            received = received.saturating_add(bytes.len());
+            if received > limit {
+                Err(io::Error::new(io::ErrorKind::InvalidData, format!("body is larger than {} bytes", limit)))
+            } else {
+                Ok(bytes)
+            }
+        },
+        Err(e) => Err(io::Error::other(e))
+    }))
+}
+
+const TAR_BLOCK: usize = 512;
+
+pub async fn summarize_tar(body: BodyStream, limit: usize) -> Result<ArchiveSummary, String> {
+    let mut archive = Archive::new(body_reader(body, limit));
+
+    let mut entries = match archive.entries() {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("could not read archive: {}", e))
+    };
+
+    let mut summary = ArchiveSummary::default();
+
+    while let Some(entry) = entries.next().await {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Err(format!("corrupted archive: {}", e))
+        };
+
+        let size = match entry.header().size() {
+            Ok(size) => size,
+            Err(e) => return Err(format!("corrupted entry header: {}", e))
+        };
+
+        // the entry has to be read through to notice an archive that was
+        // cut off in the middle of a file
+        let read = match tokio::io::copy(&mut entry, &mut tokio::io::sink()).await {
+            Ok(read) => read,
+            Err(e) => return Err(format!("truncated archive: {}", e))
+        };
+        if read != size {
+            return Err(format!("truncated archive: entry declares {} bytes but only {} were sent", size, read));
+        }
+
+        if entry.header().entry_type().is_file() {
+            summary.files += 1;
+            summary.total_size += size;
+        }
+    }
+
+    // the entries also stop at a clean end of stream, so check for the two
+    // zero blocks that close an archive: the first one has been read by now,
+    // the second one must follow
+    drop(entries);
+    let mut reader = match archive.into_inner() {
+        Ok(reader) => reader,
+        Err(_) => return Err("could not finish reading archive".to_string())
+    };
+
+    let mut block = [0u8; TAR_BLOCK];
+    match reader.read_exact(&mut block).await {
+        Ok(_) if block.iter().all(|byte| *byte == 0) => Ok(summary),
+        Ok(_) => Err("corrupted archive: data where the end-of-archive blocks belong".to_string()),
+        Err(_) => Err("truncated archive: the end-of-archive blocks are missing".to_string())
+    }
+}
+
+pub fn find_cookie(archive: &[u8]) -> Result<Option<(String, String)>, String> {
+    // the directory is removed when `dir` is dropped, on every return path
+    let dir = match TempDir::new() {
+        Ok(dir) => dir,
+        Err(e) => return Err(format!("could not create temporary directory: {}", e))
+    };
+
+    if let Err(e) = tar::Archive::new(archive).unpack(dir.path()) {
+        return Err(format!("could not unpack archive: {}", e));
+    }
+
+    let repo = match Repository::open(dir.path()) {
+        Ok(repo) => repo,
+        Err(e) => return Err(format!("archive is not a git repository: {}", e.message()))
+    };
+
+    let head = match repo.find_branch("christmas", BranchType::Local) {
+        Ok(branch) => match branch.get().peel_to_commit() {
+            Ok(commit) => commit.id(),
+            Err(e) => return Err(format!("christmas branch has no commits: {}", e.message()))
+        },
+        Err(e) => return Err(format!("christmas branch not found: {}", e.message()))
+    };
+
+    let mut revwalk = match repo.revwalk() {
+        Ok(revwalk) => revwalk,
+        Err(e) => return Err(e.message().to_string())
+    };
+    if let Err(e) = revwalk.push(head).and_then(|_| revwalk.set_sorting(Sort::TIME)) {
+        return Err(e.message().to_string());
+    }
+
+    for oid in revwalk {
+        let commit = match oid.and_then(|oid| repo.find_commit(oid)) {
+            Ok(commit) => commit,
+            Err(e) => return Err(format!("corrupted history: {}", e.message()))
+        };
+        let tree = match commit.tree() {
+            Ok(tree) => tree,
+            Err(e) => return Err(format!("corrupted tree: {}", e.message()))
+        };
+
+        let mut has_cookie = false;
+        // aborting the walk makes it return an error, which is expected here
+        let _ = tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+            if entry.name() != Some("santa.txt") {
+                return TreeWalkResult::Ok;
+            }
+
+            let mentions_cookie = entry.to_object(&repo)
+                .ok()
+                .and_then(|object| object.into_blob().ok())
+                .map(|blob| blob.content().windows(6).any(|w| w == b"COOKIE"))
+                .unwrap_or(false);
+
+            if mentions_cookie {
+                has_cookie = true;
+                TreeWalkResult::Abort
+            } else {
+                TreeWalkResult::Ok
+            }
+        });
+
+        if has_cookie {
+            let author = commit.author().name().unwrap_or_default().to_string();
+            return Ok(Some((author, commit.id().to_string())));
+        }
+    }
+
+    Ok(None)
+}
+
+pub async fn xor_lines(body: BodyStream, limit: usize) -> Result<u64, String> {
+    let mut lines = body_reader(body, limit).lines();
+    let mut line_number: u64 = 0;
+    let mut xor_accumulator: u64 = 0;
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => return Err(format!("could not read body: {}", e))
+        };
+        line_number += 1;
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match line.parse::<u64>() {
+            Ok(number) => xor_accumulator ^= number,
+            Err(_) => return Err(format!("line {}: {:?} is not a non-negative integer", line_number, line))
+        }
+    }
+
+    Ok(xor_accumulator)
+}
+
+pub const MAX_PATTERNS: usize = 100;
+
+pub fn pattern_matcher(patterns: &[String], case_insensitive: bool) -> Result<AhoCorasick, String> {
+    if patterns.len() > MAX_PATTERNS {
+        return Err(format!("at most {} patterns are allowed", MAX_PATTERNS));
+    }
+
+    if patterns.iter().any(|pattern| pattern.is_empty()) {
+        return Err("patterns cannot be empty".to_string());
+    }
+
+    match AhoCorasick::builder()
+        .ascii_case_insensitive(case_insensitive)
+        .match_kind(MatchKind::Standard)
+        .build(patterns) {
+            Ok(automaton) => Ok(automaton),
+            Err(e) => Err(format!("invalid patterns: {}", e))
+        }
+}
+
+/// Every occurrence of every pattern, including overlapping ones, with byte
+/// offsets into `text`. Case folding only applies to ASCII letters.
+pub fn find_patterns(text: &str, patterns: &[String], case_insensitive: bool) -> Result<Vec<PatternMatch>, String> {
+    let automaton = pattern_matcher(patterns, case_insensitive)?;
+
+    let mut matches: Vec<PatternMatch> = automaton.find_overlapping_iter(text)
+        .map(|found| PatternMatch {
+            pattern: patterns[found.pattern().as_usize()].clone(),
+            start: found.start(),
+            end: found.end()
+        })
+        .collect();
+
+    matches.sort_by_key(|found| (found.start, found.end));
+
+    Ok(matches)
+}
+
+const STATS_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Feeds the body to `stats` in fixed-size chunks, holding back any UTF-8
+/// sequence split across two chunks until the rest of it arrives.
+pub async fn collect_text_stats(body: BodyStream, limit: usize, mut stats: TextStats) -> Result<TextStats, String> {
+    let mut reader = body_reader(body, limit);
+    let mut buffer = vec![0u8; STATS_CHUNK_SIZE];
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(read) => read,
+            Err(e) => return Err(format!("could not read body: {}", e))
+        };
+        if read == 0 {
+            break;
+        }
+
+        pending.extend_from_slice(&buffer[..read]);
+
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(format!("invalid UTF-8 near byte {}", stats.bytes() + pending.len()))
+        };
+
+        // the prefix was validated just above
+        stats.feed(std::str::from_utf8(&pending[..valid]).unwrap_or_default());
+        pending.drain(..valid);
+    }
+
+    if !pending.is_empty() {
+        return Err("body ends in the middle of a UTF-8 character".to_string());
+    }
+
+    stats.finish();
+
+    Ok(stats)
+}
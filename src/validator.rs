@@ -0,0 +1,252 @@
+use std::{collections::BTreeMap, fs};
+
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// One recorded request and the response the challenge expects, as a line
+/// of `validation/cases.jsonl`.
+#[derive(Debug, Deserialize)]
+pub struct Case {
+    pub day: i32,
+    pub name: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Binary request bodies such as multipart uploads or tar archives.
+    #[serde(default, deserialize_with = "from_base64")]
+    pub body_base64: Option<Vec<u8>>,
+    #[serde(default)]
+    pub json: Option<Value>,
+    pub expect: Expectation
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Expectation {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub json: Option<Value>
+}
+
+fn from_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+
+    match general_purpose::STANDARD.decode(&encoded) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) => Err(serde::de::Error::custom(format!("body_base64 is not base64: {}", e)))
+    }
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+pub fn load_cases(path: &str) -> Result<Vec<Case>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("could not read {}: {}", path, e))
+    };
+
+    parse_cases(&contents).map_err(|reason| format!("{} {}", path, reason))
+}
+
+/// One case per line; blank lines and lines starting with `#` are skipped.
+pub fn parse_cases(contents: &str) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let case = match serde_json::from_str::<Case>(line) {
+            Ok(case) => case,
+            Err(e) => return Err(format!("line {}: {}", index + 1, e))
+        };
+
+        let bodies = [case.body.is_some(), case.body_base64.is_some(), case.json.is_some()];
+        if bodies.iter().filter(|&&set| set).count() > 1 {
+            return Err(format!("line {}: only one of body, body_base64 and json can be set", index + 1));
+        }
+
+        cases.push(case);
+    }
+
+    Ok(cases)
+}
+
+/// Sends the case to `base_url` and describes every way the response differs
+/// from the expectation; an empty list means the case passed.
+pub async fn run_case(client: &Client, base_url: &str, case: &Case) -> Vec<String> {
+    let method = match Method::from_bytes(case.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => return vec![format!("unknown method {:?}", case.method)]
+    };
+
+    let mut request = client.request(method, format!("{}{}", base_url.trim_end_matches('/'), case.path));
+    for (name, value) in &case.headers {
+        request = request.header(name, value);
+    }
+    if let Some(json) = &case.json {
+        request = request.header("Content-Type", "application/json").body(json.to_string());
+    } else if let Some(body) = &case.body {
+        request = request.body(body.clone());
+    } else if let Some(bytes) = &case.body_base64 {
+        request = request.body(bytes.clone());
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return vec![format!("request failed: {}", e)]
+    };
+
+    let mut failures = Vec::new();
+
+    if response.status().as_u16() != case.expect.status {
+        failures.push(format!("status {} != expected {}", response.status().as_u16(), case.expect.status));
+    }
+
+    for (name, expected) in &case.expect.headers {
+        let actual = response.headers().get(name).and_then(|value| value.to_str().ok());
+        if actual != Some(expected.as_str()) {
+            failures.push(format!("header {}: {:?} != expected {:?}", name, actual, expected));
+        }
+    }
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => {
+            failures.push(format!("could not read body: {}", e));
+            return failures;
+        }
+    };
+
+    if let Some(expected) = &case.expect.json {
+        match serde_json::from_str::<Value>(&body) {
+            Ok(actual) if actual == *expected => (),
+            Ok(actual) => failures.push(format!(
+                "json differs:\n{}",
+                diff(&pretty(expected), &pretty(&actual))
+            )),
+            Err(_) => failures.push(format!("body is not json:\n{}", diff(&pretty(expected), &body)))
+        }
+    } else if let Some(expected) = &case.expect.body {
+        if body != *expected {
+            failures.push(format!("body differs:\n{}", diff(expected, &body)));
+        }
+    }
+
+    failures
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Line by line comparison, `-` for expected and `+` for actual lines.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut lines = Vec::new();
+
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(left), Some(right)) if left == right => lines.push(format!("  {}", left)),
+            (left, right) => {
+                if let Some(left) = left {
+                    lines.push(format!("- {}", left));
+                }
+                if let Some(right) = right {
+                    lines.push(format!("+ {}", right));
+                }
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = r#"{"day": 1, "name": "cube the bits", "path": "/1/4/8", "expect": {"body": "1728"}}"#;
+
+    #[test]
+    fn parses_cases_around_comments_and_blank_lines() {
+        let contents = format!("# day 1\n\n{}\n  # indented\n{}\n", VALID, VALID);
+
+        let cases = parse_cases(&contents).unwrap();
+
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].method, "GET");
+        assert_eq!(cases[0].expect.status, 200);
+        assert_eq!(cases[0].expect.body.as_deref(), Some("1728"));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_invalid_case() {
+        let missing_path = r#"{"day": 1, "name": "no path", "expect": {}}"#;
+        let contents = format!("{}\n\n{}\n", VALID, missing_path);
+
+        let error = parse_cases(&contents).unwrap_err();
+
+        assert!(error.starts_with("line 3: missing field `path`"), "{}", error);
+        assert!(parse_cases("{not json").unwrap_err().starts_with("line 1: "));
+    }
+
+    #[test]
+    fn decodes_base64_bodies() {
+        let line = r#"{"day": 20, "name": "bytes", "path": "/", "body_base64": "AAEC/w==", "expect": {}}"#;
+        assert_eq!(parse_cases(line).unwrap()[0].body_base64, Some(vec![0, 1, 2, 255]));
+
+        let invalid = r#"{"day": 20, "name": "bytes", "path": "/", "body_base64": "not base64!", "expect": {}}"#;
+        let error = parse_cases(&format!("{}\n{}", VALID, invalid)).unwrap_err();
+        assert!(error.starts_with("line 2: body_base64 is not base64"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_more_than_one_body() {
+        let line = r#"{"day": 6, "name": "both", "path": "/6", "body": "elf", "json": {}, "expect": {}}"#;
+
+        let error = parse_cases(line).unwrap_err();
+
+        assert_eq!(error, "line 1: only one of body, body_base64 and json can be set");
+    }
+
+    #[test]
+    fn reports_missing_files_with_their_path() {
+        let error = load_cases("validation/missing.jsonl").unwrap_err();
+
+        assert!(error.starts_with("could not read validation/missing.jsonl: "), "{}", error);
+    }
+
+    #[test]
+    fn diffs_line_by_line() {
+        assert_eq!(diff("a\nb", "a\nb"), "  a\n  b");
+        assert_eq!(diff("a\nb\nc", "a\nx\nc"), "  a\n- b\n+ x\n  c");
+        assert_eq!(diff("a", "a\nextra"), "  a\n+ extra");
+        assert_eq!(diff("a\ngone", "a"), "  a\n- gone");
+    }
+
+    #[test]
+    fn diffs_pretty_printed_json() {
+        let expected = pretty(&serde_json::json!({"elf": 4, "shelf": 1}));
+        let actual = pretty(&serde_json::json!({"elf": 5, "shelf": 1}));
+
+        assert_eq!(diff(&expected, &actual), "  {\n-   \"elf\": 4,\n+   \"elf\": 5,\n    \"shelf\": 1\n  }");
+    }
+}
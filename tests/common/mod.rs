@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+
+use std::{
+    env,
+    net::{SocketAddr, TcpListener},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Duration
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::Path,
+    http::{header, HeaderMap, Request, StatusCode},
+    routing::get,
+    Json, Router
+};
+use sebatustra_christmas::{build_router, build_state, config::AppConfig, migrate};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes
+}
+
+impl TestResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|e| panic!("{} is not json: {}", self.text(), e))
+    }
+}
+
+pub async fn call(app: &Router, request: Request<Body>) -> TestResponse {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    TestResponse { status, headers, body }
+}
+
+pub async fn get_uri(app: &Router, uri: &str) -> TestResponse {
+    call(app, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+pub async fn post_json(app: &Router, uri: &str, body: Value) -> TestResponse {
+    let request = Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    call(app, request).await
+}
+
+pub async fn post_text(app: &Router, uri: &str, body: impl Into<Body>) -> TestResponse {
+    call(app, Request::post(uri).body(body.into()).unwrap()).await
+}
+
+pub fn config(pokeapi_url: &str) -> AppConfig {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    AppConfig {
+        asset_root: root.join("assets"),
+        pokeapi_url: pokeapi_url.to_string(),
+        policies_path: root.join("policies.toml").display().to_string(),
+        breached_path: root.join("breached_passwords.txt").display().to_string(),
+        upstream_timeout_secs: 2,
+        ..AppConfig::default()
+    }
+}
+
+pub fn app(pool: PgPool, config: AppConfig) -> Router {
+    build_router(build_state(pool, config).unwrap())
+}
+
+/// A pool that never connects, for routes that do not touch the database.
+pub fn unreachable_pool() -> PgPool {
+    PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://localhost:1/christmas")
+        .unwrap()
+}
+
+/// A pool whose server accepts connections but never answers, for checks
+/// that must give up on a database that hangs.
+pub async fn hanging_pool() -> PgPool {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(30))
+        .connect_lazy(&format!("postgres://postgres@{}/christmas", address))
+        .unwrap()
+}
+
+/// Serves just enough of PokeAPI for Day 8: Pikachu (#25) weighs 60
+/// hectograms and every other id is missing.
+pub async fn mock_pokeapi() -> String {
+    let mock = Router::new().route("/api/v2/pokemon/:id", get(|Path(id): Path<u64>| async move {
+        match id {
+            25 => Ok(Json(json!({"name": "pikachu", "weight": 60}))),
+            _ => Err(StatusCode::NOT_FOUND)
+        }
+    }));
+
+    format!("http://{}/api/v2", serve(mock))
+}
+
+/// Serves the router on a free local port, for clients that need a real
+/// socket such as WebSockets.
+pub fn serve(app: Router) -> SocketAddr {
+    let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .serve(app.into_make_service());
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    address
+}
+
+/// A migrated Postgres database, either the one in `TEST_DATABASE_URL` (its
+/// tables are wiped) or a throwaway cluster started with `initdb`/`pg_ctl`
+/// and stopped again when this is dropped. Panics when neither is available,
+/// so a missing database never reads as a pass.
+pub struct TestDatabase {
+    pub pool: PgPool,
+    cluster: Option<Cluster>
+}
+
+struct Cluster {
+    dir: TempDir
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        let _ = Command::new("pg_ctl")
+            .arg("stop")
+            .arg("-D").arg(self.dir.path().join("data"))
+            .args(["-m", "immediate", "-s"])
+            .status();
+    }
+}
+
+impl TestDatabase {
+    pub async fn start() -> Self {
+        let (url, cluster) = match env::var("TEST_DATABASE_URL") {
+            Ok(url) => (url, None),
+            Err(_) => match start_cluster() {
+                Ok((url, cluster)) => (url, Some(cluster)),
+                Err(reason) => panic!("no Postgres to test against, set TEST_DATABASE_URL: {}", reason)
+            }
+        };
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect(&url)
+            .await
+            .unwrap();
+
+        migrate(&pool).await.unwrap();
+        sqlx::query("TRUNCATE reindeer, contests, chat_messages RESTART IDENTITY;")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        TestDatabase { pool, cluster }
+    }
+}
+
+fn start_cluster() -> Result<(String, Cluster), String> {
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return Err(format!("no temp dir: {}", e))
+    };
+    let data = dir.path().join("data");
+
+    let initdb = Command::new("initdb")
+        .arg("-D").arg(&data)
+        .args(["-U", "postgres", "--auth=trust", "--no-sync", "--encoding=UTF8", "--locale=C"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match initdb {
+        Ok(status) if status.success() => (),
+        Ok(status) => return Err(format!("initdb exited with {} (it refuses to run as root)", status)),
+        Err(e) => return Err(format!("initdb is not available: {}", e))
+    }
+
+    let port = match TcpListener::bind("127.0.0.1:0").and_then(|listener| listener.local_addr()) {
+        Ok(address) => address.port(),
+        Err(e) => return Err(format!("no free port: {}", e))
+    };
+
+    // the cluster exists from here on, so dropping it stops the server
+    let cluster = Cluster { dir };
+
+    let options = format!(
+        "-p {} -c listen_addresses=127.0.0.1 -k {} -F",
+        port,
+        cluster.dir.path().display()
+    );
+    let started = Command::new("pg_ctl")
+        .arg("start")
+        .arg("-D").arg(&data)
+        .arg("-l").arg(cluster.dir.path().join("postgres.log"))
+        .args(["-w", "-s", "-o", &options])
+        .status();
+    match started {
+        Ok(status) if status.success() => (),
+        Ok(status) => return Err(format!("pg_ctl start exited with {}", status)),
+        Err(e) => return Err(format!("pg_ctl is not available: {}", e))
+    }
+
+    Ok((format!("postgres://postgres@127.0.0.1:{}/postgres", port), cluster))
+}
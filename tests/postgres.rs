@@ -0,0 +1,242 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{body::Body, http::{Request, StatusCode}, Router};
+use futures_util::{SinkExt, StreamExt};
+use sebatustra_christmas::{
+    build_router,
+    build_state,
+    state::{ChatMessage, ChatState},
+    validator::{load_cases, run_case}
+};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use ulid::Ulid;
+
+use common::{app, call, config, get_uri, mock_pokeapi, post_json, post_text, serve, TestDatabase};
+
+fn reindeer(name: &str, speed: f64, candies: u64) -> Value {
+    json!({
+        "name": name, "strength": 5, "speed": speed, "height": 80, "antler_width": 36,
+        "snow_magic_power": 9001, "favorite_food": "hay", "cAnD13s_3ATeN-yesT3rdAy": candies
+    })
+}
+
+async fn days_13_and_18_track_orders(app: &Router) {
+    assert_eq!(get_uri(app, "/13/sql").await.text(), "20231213");
+    assert_eq!(post_text(app, "/18/reset", "").await.status, StatusCode::OK);
+
+    let regions = post_json(app, "/18/regions", json!([
+        {"id": 1, "name": "North Pole"},
+        {"id": 2, "name": "Europe"},
+        {"id": 3, "name": "Antarctica"}
+    ])).await;
+    assert_eq!(regions.status, StatusCode::OK);
+
+    let orders = post_json(app, "/18/orders", json!([
+        {"id": 1, "region_id": 1, "gift_name": "Board Game", "quantity": 5},
+        {"id": 2, "region_id": 2, "gift_name": "Toy Train", "quantity": 3},
+        {"id": 3, "region_id": 2, "gift_name": "Board Game", "quantity": 2}
+    ])).await;
+    assert_eq!(orders.status, StatusCode::OK);
+
+    assert_eq!(get_uri(app, "/13/orders/total").await.json(), json!({"total": 10}));
+    assert_eq!(get_uri(app, "/13/orders/popular").await.json(), json!({"popular": "Board Game"}));
+    assert_eq!(get_uri(app, "/18/regions/total").await.json(), json!([
+        {"region": "Europe", "total": 5},
+        {"region": "North Pole", "total": 5}
+    ]));
+}
+
+async fn day_4_keeps_a_registry_and_contest_history(app: &Router) {
+    assert_eq!(post_json(app, "/4/reindeer", reindeer("Dasher", 50.4, 2)).await.status, StatusCode::OK);
+    assert_eq!(post_json(app, "/4/reindeer", reindeer("Dancer", 48.2, 5)).await.status, StatusCode::OK);
+    assert_eq!(post_json(app, "/4/reindeer", reindeer("Dancer", 1.0, 1)).await.status, StatusCode::BAD_REQUEST);
+
+    let dasher = get_uri(app, "/4/reindeer/Dasher").await.json();
+    assert_eq!(dasher["speed"], json!(50.4));
+    assert_eq!(get_uri(app, "/4/reindeer").await.json().as_array().map(Vec::len), Some(2));
+
+    let first = post_json(app, "/4/contests", json!([])).await.json();
+    assert_eq!(first["result"]["fastest"], json!("Speeding past the finish line with a strength of 5 is Dasher"));
+
+    let update = Request::put("/4/reindeer/Dancer")
+        .header("content-type", "application/json")
+        .body(Body::from(reindeer("Dancer", 60.0, 5).to_string()))
+        .unwrap();
+    assert_eq!(call(app, update).await.status, StatusCode::OK);
+
+    let second = post_json(app, "/4/contests", json!([])).await.json();
+    assert_eq!(second["result"]["fastest"], json!("Speeding past the finish line with a strength of 5 is Dancer"));
+
+    assert_eq!(get_uri(app, "/4/contests").await.json().as_array().map(Vec::len), Some(2));
+    assert_eq!(get_uri(app, &format!("/4/contests/{}", first["id"])).await.status, StatusCode::OK);
+
+    let history = get_uri(app, "/4/reindeer/Dasher/history").await.json();
+    assert_eq!(history["history"].as_array().map(Vec::len), Some(2));
+    assert_eq!(history["history"][0]["change"], Value::Null);
+    assert_eq!(history["history"][1]["change"]["fastest"], json!(-1));
+
+    let leaderboard = get_uri(app, "/4/leaderboard").await.json();
+    assert_eq!(leaderboard.as_array().map(Vec::len), Some(2));
+    assert_eq!(leaderboard[0]["contests"], json!(2));
+
+    let delete = Request::delete("/4/reindeer/Dasher").body(Body::empty()).unwrap();
+    assert_eq!(call(app, delete).await.status, StatusCode::OK);
+    assert_eq!(get_uri(app, "/4/reindeer/Dasher").await.status, StatusCode::NOT_FOUND);
+}
+
+async fn day_19_pages_empty_history(app: &Router) {
+    let page = get_uri(app, "/19/history/lobby").await.json();
+    assert_eq!(page, json!({"room": "lobby", "messages": [], "next": null}));
+
+    assert_eq!(get_uri(app, "/19/history/lobby?before=nope").await.status, StatusCode::BAD_REQUEST);
+}
+
+async fn readiness_passes_once_migrated(app: &Router) {
+    let ready = get_uri(app, "/readyz").await;
+    assert_eq!(ready.status, StatusCode::OK);
+    assert_eq!(ready.json()["status"], json!("ready"));
+}
+
+async fn day_19_pages_through_stored_history(app: &Router, pool: &PgPool) {
+    for index in 0..25u64 {
+        sqlx::query("INSERT INTO chat_messages (id, room, username, message) VALUES ($1, 'attic', 'elf', $2);")
+            .bind(Ulid::from_parts(1_700_000_000_000 + index, 0).to_string())
+            .bind(format!("message {}", index))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    let first = get_uri(app, "/19/history/attic").await.json();
+    let messages = first["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 20);
+    assert_eq!(messages[0]["message"], json!("message 24"));
+    assert_eq!(messages[19]["message"], json!("message 5"));
+
+    let cursor = first["next"].as_str().unwrap();
+    let second = get_uri(app, &format!("/19/history/attic?before={}", cursor)).await.json();
+    let messages = second["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 5);
+    assert_eq!(messages[0]["message"], json!("message 4"));
+    assert_eq!(messages[4]["message"], json!("message 0"));
+    assert_eq!(second["next"], Value::Null);
+}
+
+// Under most non-C collations punctuation sorts before every ULID, which is
+// where an uncursored page used to come back empty. The shared schema keeps
+// the cluster's C collation, so this pages through a copy of the table in a
+// schema of its own that is dropped again afterwards.
+async fn day_19_pages_under_icu_collation(pool: &PgPool) {
+    let icu: bool = sqlx::query_scalar("
+        SELECT getdatabaseencoding() = 'UTF8'
+            AND EXISTS (SELECT 1 FROM pg_collation WHERE collname = 'und-x-icu');
+    ")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    if !icu {
+        println!("skipping the ICU collation check, the database has no und-x-icu collation");
+        return;
+    }
+
+    for statement in [
+        "DROP SCHEMA IF EXISTS icu_collation CASCADE;",
+        "CREATE SCHEMA icu_collation;",
+        "CREATE TABLE icu_collation.chat_messages (LIKE public.chat_messages INCLUDING ALL);",
+        r#"ALTER TABLE icu_collation.chat_messages ALTER COLUMN id TYPE VARCHAR(26) COLLATE "und-x-icu";"#
+    ] {
+        sqlx::query(statement).execute(pool).await.unwrap();
+    }
+
+    let options = pool.connect_options().as_ref().clone().options([("search_path", "icu_collation")]);
+    let scoped = PgPoolOptions::new().max_connections(2).connect_with(options).await.unwrap();
+    let app = app(scoped.clone(), config(&mock_pokeapi().await));
+
+    day_19_pages_through_stored_history(&app, &scoped).await;
+
+    scoped.close().await;
+    sqlx::query("DROP SCHEMA icu_collation CASCADE;").execute(pool).await.unwrap();
+}
+
+async fn day_19_skips_replayed_messages_on_the_live_feed(app: &Router, chat: &ChatState, pool: &PgPool) {
+    let ids: Vec<Ulid> = (0..3u64).map(|index| Ulid::from_parts(1_700_000_100_000 + index, 0)).collect();
+    for (index, id) in ids.iter().enumerate() {
+        sqlx::query("INSERT INTO chat_messages (id, room, username, message) VALUES ($1, 'loft', 'elf', $2);")
+            .bind(id.to_string())
+            .bind(format!("message {}", index))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    let (live, _watcher) = chat.join("loft").await;
+    let address = serve(app.clone());
+    let url = format!("ws://{}/19/ws/room/loft/user/blitzen?history=10", address);
+    let (mut client, _) = connect_async(url).await.unwrap();
+
+    // once blitzen has subscribed, the newest stored message arrives live as
+    // well, the way it does when it is sent while the history is read
+    for _ in 0..100 {
+        if live.receiver_count() == 2 {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(live.receiver_count(), 2);
+    live.send(ChatMessage { id: ids[2], user: "elf".to_string(), message: "message 2".to_string() }).unwrap();
+
+    client.send(Message::Text(json!({"message": "fresh"}).to_string())).await.unwrap();
+
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        match timeout(Duration::from_secs(2), client.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => received.push(serde_json::from_str::<Value>(&text).unwrap()),
+            other => panic!("expected a chat message, got {:?}", other)
+        }
+    }
+    assert_eq!(received, vec![
+        json!({"user": "elf", "message": "message 0"}),
+        json!({"user": "elf", "message": "message 1"}),
+        json!({"user": "elf", "message": "message 2"}),
+        json!({"user": "blitzen", "message": "fresh"})
+    ]);
+
+    client.close(None).await.unwrap();
+}
+
+async fn recorded_database_cases_pass(app: &Router) {
+    let base_url = format!("http://{}", serve(app.clone()));
+    let client = reqwest::Client::new();
+    let cases = load_cases(concat!(env!("CARGO_MANIFEST_DIR"), "/validation/cases.jsonl")).unwrap();
+
+    for case in cases.iter().filter(|case| [13, 18].contains(&case.day)) {
+        let failures = run_case(&client, &base_url, case).await;
+        assert!(failures.is_empty(), "day {} {}: {}", case.day, case.name, failures.join("\n"));
+    }
+}
+
+// One test owns the database so the scenarios run in order against it.
+// Run with `cargo test -- --ignored` and either TEST_DATABASE_URL set or
+// initdb/pg_ctl on the PATH as a non-root user.
+#[tokio::test]
+#[ignore = "needs Postgres"]
+async fn postgres_backed_routes() {
+    let database = TestDatabase::start().await;
+    let state = build_state(database.pool.clone(), config(&mock_pokeapi().await)).unwrap();
+    let chat = state.chat.clone();
+    let app = build_router(state);
+
+    readiness_passes_once_migrated(&app).await;
+    days_13_and_18_track_orders(&app).await;
+    recorded_database_cases_pass(&app).await;
+    day_4_keeps_a_registry_and_contest_history(&app).await;
+    day_19_pages_empty_history(&app).await;
+    day_19_pages_through_stored_history(&app, &database.pool).await;
+    day_19_pages_under_icu_collation(&database.pool).await;
+    day_19_skips_replayed_messages_on_the_live_feed(&app, &chat, &database.pool).await;
+}
@@ -0,0 +1,471 @@
+mod common;
+
+use std::{fs, io::Write, time::{Duration, Instant}};
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+
+use common::{app, call, config, get_uri, hanging_pool, mock_pokeapi, post_json, post_text, unreachable_pool};
+
+async fn test_app() -> Router {
+    app(unreachable_pool(), config(&mock_pokeapi().await))
+}
+
+fn contest_roster() -> Value {
+    json!([
+        {"name": "Dasher", "strength": 5, "speed": 50.4, "height": 80, "antler_width": 36,
+         "snow_magic_power": 9001, "favorite_food": "hay", "cAnD13s_3ATeN-yesT3rdAy": 2},
+        {"name": "Dancer", "strength": 6, "speed": 48.2, "height": 65, "antler_width": 37,
+         "snow_magic_power": 4004, "favorite_food": "grass", "cAnD13s_3ATeN-yesT3rdAy": 5}
+    ])
+}
+
+#[tokio::test]
+async fn day_minus_one_always_fails() {
+    let app = test_app().await;
+
+    assert_eq!(get_uri(&app, "/-1/error").await.status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn day_1_evaluates_bit_expressions() {
+    let app = test_app().await;
+
+    assert_eq!(get_uri(&app, "/1/4/8").await.text(), "1728");
+    assert_eq!(get_uri(&app, "/1/10").await.text(), "1000");
+    assert_eq!(get_uri(&app, "/1/4/5/8/10").await.text(), "27");
+    assert_eq!(get_uri(&app, "/1/3/5?op=sum&pow=2").await.text(), "64");
+    assert_eq!(get_uri(&app, "/1/4/x").await.status, StatusCode::BAD_REQUEST);
+
+    let explained = get_uri(&app, "/1/4/8?explain=true").await.json();
+    assert_eq!(explained["result"], json!("1728"));
+}
+
+#[tokio::test]
+async fn day_4_sums_and_compares_reindeer() {
+    let app = test_app().await;
+
+    let strength = post_json(&app, "/4/strength", json!([
+        {"name": "Dasher", "strength": 5},
+        {"name": "Dancer", "strength": 6},
+        {"name": "Dasher", "strength": 7}
+    ])).await;
+    assert_eq!(strength.text(), "18");
+
+    let grouped = post_json(&app, "/4/strength?mode=grouped&format=json", json!([
+        {"name": "Dasher", "strength": 5},
+        {"name": "Dasher", "strength": 7}
+    ])).await.json();
+    assert_eq!(grouped["groups"], json!({"Dasher": 12}));
+
+    let overflow = post_json(&app, "/4/strength", json!([
+        {"name": "Dasher", "strength": u64::MAX},
+        {"name": "Dancer", "strength": 1}
+    ])).await;
+    assert_eq!(overflow.status, StatusCode::BAD_REQUEST);
+
+    let contest = post_json(&app, "/4/contest", contest_roster()).await.json();
+    assert_eq!(contest, json!({
+        "fastest": "Speeding past the finish line with a strength of 5 is Dasher",
+        "tallest": "Dasher is standing tall with his 36 cm wide antlers",
+        "magician": "Dasher could blast you away with a snow magic power of 9001",
+        "consumer": "Dancer ate lots of candies, but also some grass"
+    }));
+
+    let analytics = post_json(&app, "/4/contest/analytics", contest_roster()).await;
+    assert_eq!(analytics.status, StatusCode::OK);
+
+    let compat = post_json(&app, "/4/contest/analytics?mode=compat", contest_roster()).await.json();
+    assert_eq!(compat, contest);
+}
+
+#[tokio::test]
+async fn day_5_paginates_any_array() {
+    let app = test_app().await;
+    let names = json!(["Ava", "Caleb", "Mia", "Owen", "Lily", "Ethan", "Zoe", "Nolan", "Harper", "Lucas"]);
+
+    let page = post_json(&app, "/5?offset=3&limit=5", names.clone()).await.json();
+    assert_eq!(page, json!(["Owen", "Lily", "Ethan", "Zoe", "Nolan"]));
+
+    let split = post_json(&app, "/5?split=4", names.clone()).await.json();
+    assert_eq!(split, json!([
+        ["Ava", "Caleb", "Mia", "Owen"],
+        ["Lily", "Ethan", "Zoe", "Nolan"],
+        ["Harper", "Lucas"]
+    ]));
+
+    let mixed = post_json(&app, "/5?offset=1", json!([1, {"a": true}, null])).await.json();
+    assert_eq!(mixed, json!([{"a": true}, null]));
+
+    assert_eq!(post_json(&app, "/5?offset=11", names.clone()).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(post_json(&app, "/5?split=0", names.clone()).await.status, StatusCode::BAD_REQUEST);
+
+    let envelope = post_json(&app, "/5?offset=0&limit=4&meta=envelope", names.clone()).await.json();
+    assert_eq!(envelope["total"], json!(10));
+    assert_eq!(envelope["next_offset"], json!(4));
+    assert_eq!(envelope["pages"], json!(3));
+
+    let unbounded = post_json(&app, &format!("/5?limit={}&meta=envelope", usize::MAX), names.clone()).await;
+    assert_eq!(unbounded.status, StatusCode::OK);
+    assert_eq!(unbounded.json()["pages"], json!(1));
+    assert_eq!(unbounded.json()["data"].as_array().map(Vec::len), Some(10));
+
+    let headers = post_json(&app, "/5?limit=4&meta=header", names).await.headers;
+    assert_eq!(headers["x-total-count"], "10");
+    assert_eq!(headers[header::LINK], "</5?offset=4&limit=4&meta=header>; rel=\"next\"");
+}
+
+#[tokio::test]
+async fn day_6_counts_elves_and_patterns() {
+    let app = test_app().await;
+
+    let elves = post_text(&app, "/6", "The mischievous elf peeked out from behind the toy workshop,
+      and another elf joined in the festive dance.
+      Look, there is also an elf on that shelf!").await.json();
+    assert_eq!(elves, json!({"elf": 4, "elf on a shelf": 0, "shelf with no elf on it": 1}));
+
+    let overlapping = post_text(&app, "/6", "elf on a shelf on a shelf").await.json();
+    assert_eq!(overlapping["elf on a shelf"], json!(2));
+
+    let patterns = post_json(&app, "/6/patterns", json!({
+        "text": "Ho ho HO",
+        "patterns": ["ho"],
+        "case_insensitive": true
+    })).await.json();
+    assert_eq!(patterns["counts"], json!({"ho": 3}));
+    assert_eq!(patterns["matches"][2], json!({"pattern": "ho", "start": 6, "end": 8}));
+
+    let stats = post_text(&app, "/6/stats?top=1&patterns=elf", "elf on a shelf\nelf\n").await.json();
+    assert_eq!(stats["lines"], json!(2));
+    assert_eq!(stats["words"], json!(5));
+    assert_eq!(stats["top_words"], json!([{"term": "elf", "count": 2}]));
+    assert_eq!(stats["patterns"]["elf"], json!({"count": 3, "positions": [0, 11, 15]}));
+}
+
+fn recipe_cookie(recipe: Value) -> String {
+    format!("recipe={}", general_purpose::STANDARD.encode(recipe.to_string()))
+}
+
+#[tokio::test]
+async fn day_7_decodes_and_bakes_cookies() {
+    let app = test_app().await;
+
+    let decode = Request::get("/7/decode")
+        .header(header::COOKIE, recipe_cookie(json!({"flour": 100, "chocolate chips": 20})))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(call(&app, decode).await.json(), json!({"flour": 100, "chocolate chips": 20}));
+
+    let bake = Request::get("/7/bake")
+        .header(header::COOKIE, recipe_cookie(json!({
+            "recipe": {"flour": 95, "sugar": 50, "butter": 30, "baking powder": 10, "chocolate chips": 50},
+            "pantry": {"flour": 385, "sugar": 507, "butter": 2122, "baking powder": 865, "chocolate chips": 457}
+        })))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(call(&app, bake).await.json(), json!({
+        "cookies": 4,
+        "pantry": {"flour": 5, "sugar": 307, "butter": 2002, "baking powder": 825, "chocolate chips": 257}
+    }));
+}
+
+#[tokio::test]
+async fn day_8_weighs_pokemon_through_the_mock_api() {
+    let app = test_app().await;
+
+    assert_eq!(get_uri(&app, "/8/weight/25").await.text(), "6");
+    assert_eq!(get_uri(&app, "/8/drop/25").await.text(), "84.10707461325713");
+    assert_eq!(get_uri(&app, "/8/weight/9999").await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn day_11_serves_and_inspects_images() {
+    let app = test_app().await;
+
+    let image = get_uri(&app, "/11/assets/decoration.png").await;
+    assert_eq!(image.status, StatusCode::OK);
+    assert_eq!(image.headers[header::CONTENT_TYPE], "image/png");
+    assert_eq!(get_uri(&app, "/11/assets/..%2FCargo.toml").await.status, StatusCode::NOT_FOUND);
+
+    let png = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/decoration.png")).unwrap();
+    let mut body = b"--boundary\r\nContent-Disposition: form-data; name=\"image\"; filename=\"decoration.png\"\r\nContent-Type: image/png\r\n\r\n".to_vec();
+    body.extend_from_slice(&png);
+    body.extend_from_slice(b"\r\n--boundary--\r\n");
+
+    let upload = Request::post("/11/red_pixels")
+        .header(header::CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+        .body(Body::from(body))
+        .unwrap();
+    assert_eq!(call(&app, upload).await.text(), "73034");
+}
+
+#[tokio::test]
+async fn day_12_converts_and_analyses_ulids() {
+    let app = test_app().await;
+
+    let uuids = post_json(&app, "/12/ulids", json!([
+        "01BJQ0E1C3Z56ABCD0E11HYX4M",
+        "01BJQ0E1C3Z56ABCD0E11HYX5N",
+        "01BJQ0E1C3Z56ABCD0E11HYX6Q",
+        "01BJQ0E1C3Z56ABCD0E11HYX7R",
+        "01BJQ0E1C3Z56ABCD0E11HYX8P"
+    ])).await.json();
+    assert_eq!(uuids, json!([
+        "015cae07-0583-f94c-a5b1-a070431f7516",
+        "015cae07-0583-f94c-a5b1-a070431f74f8",
+        "015cae07-0583-f94c-a5b1-a070431f74d7",
+        "015cae07-0583-f94c-a5b1-a070431f74b5",
+        "015cae07-0583-f94c-a5b1-a070431f7494"
+    ]));
+
+    let analysis = post_json(&app, "/12/ulids/5", json!([
+        "00WEGGF0G0J5HEYXS3D7RWZGV8",
+        "76EP4G39R8JD1N8AQNYDVJBRCF",
+        "018CJ7KMG0051CDCS3B7BFJ3AK",
+        "00Y986KPG0AMGB78RD45E9109K",
+        "010451HTG0NQ7H2XTW0XTG5Y6M",
+        "01HH9SJEG0KY16H81S3N1BMXM4",
+        "01HH9SJEG0P9M22Z9VGHH9C8CX",
+        "017F8YY0G0NQA16HHC2QT5JD6X",
+        "03QCPC7P003V1NND3B3QJW72QJ"
+    ])).await.json();
+    assert_eq!(analysis, json!({"christmas eve": 3, "weekday": 1, "in the future": 2, "LSB is 1": 5}));
+}
+
+#[tokio::test]
+async fn day_14_renders_html() {
+    let app = test_app().await;
+    let content = json!({"content": "<h1>Welcome to the North Pole!</h1>"});
+
+    let unsafe_html = post_json(&app, "/14/unsafe", content.clone()).await;
+    assert_eq!(unsafe_html.headers[header::CONTENT_TYPE], "text/html");
+    assert!(unsafe_html.text().contains("<h1>Welcome to the North Pole!</h1>"));
+
+    let safe_html = post_json(&app, "/14/safe", content).await.text();
+    assert!(safe_html.contains("&lt;h1&gt;Welcome to the North Pole!&lt;/h1&gt;"));
+}
+
+#[tokio::test]
+async fn day_15_judges_passwords() {
+    let app = test_app().await;
+
+    let nice = post_json(&app, "/15/nice", json!({"input": "hello there"})).await;
+    assert_eq!((nice.status, nice.json()), (StatusCode::OK, json!({"result": "nice"})));
+
+    let naughty = post_json(&app, "/15/nice", json!({"input": "abcd"})).await;
+    assert_eq!((naughty.status, naughty.json()), (StatusCode::BAD_REQUEST, json!({"result": "naughty"})));
+
+    assert_eq!(post_json(&app, "/15/nice/unknown", json!({"input": "x"})).await.status, StatusCode::NOT_FOUND);
+
+    let batch = post_json(&app, "/15/nice/default/batch", json!({"inputs": ["hello there", "abcd"]})).await.json();
+    assert_eq!((batch["nice"].clone(), batch["naughty"].clone()), (json!(1), json!(1)));
+
+    assert_eq!(post_json(&app, "/15/game", json!({"input": "password"})).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(post_json(&app, "/15/game", json!({"input": "Ab2023x0"})).await.status, StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(post_json(&app, "/15/game", json!({"input": "Ab2000x1x1x1x20"})).await.status, StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+    let strength = post_json(&app, "/15/strength", json!({"input": "password"})).await.json();
+    assert_eq!(strength["breached"], json!(true));
+
+    let shelf = post_json(&app, "/15/strength", json!({"input": "shelf"})).await.json();
+    assert_eq!(shelf["patterns"], json!(["dictionary word \"shelf\""]));
+
+    let repeated = post_json(&app, "/15/strength", json!({"input": "xyzxyz"})).await.json();
+    assert_eq!(repeated["patterns"], json!(["sequence \"xyz\"", "sequence \"xyz\"", "repeated \"xyzxyz\""]));
+
+    let too_long = post_json(&app, "/15/strength", json!({"input": "a".repeat(257)})).await;
+    assert_eq!(too_long.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn day_19_counts_views_and_requires_an_upgrade() {
+    let app = test_app().await;
+
+    assert!(get_uri(&app, "/19/ws/ping").await.status.is_client_error());
+    assert_eq!(post_text(&app, "/19/reset", "").await.status, StatusCode::OK);
+    assert_eq!(get_uri(&app, "/19/views").await.text(), "0");
+}
+
+fn tar_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *contents).unwrap();
+    }
+
+    builder.into_inner().unwrap()
+}
+
+fn commit_file(repo: &git2::Repository, path: &str, contents: &str, author: &str, parent: Option<git2::Oid>) -> git2::Oid {
+    let workdir = repo.workdir().unwrap();
+    let mut file = fs::File::create(workdir.join(path)).unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.add_path(std::path::Path::new(path)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+    let signature = git2::Signature::now(author, &format!("{}@north.pole", author.to_lowercase())).unwrap();
+    let parents: Vec<git2::Commit> = parent.map(|oid| repo.find_commit(oid).unwrap()).into_iter().collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+    repo.commit(None, &signature, &signature, path, &tree, &parents).unwrap()
+}
+
+#[tokio::test]
+async fn day_20_inspects_archives_and_finds_the_cookie() {
+    let app = test_app().await;
+
+    let archive = tar_archive(&[("a.txt", b"hello"), ("b/c.txt", &[0; 1000])]);
+    assert_eq!(post_text(&app, "/20/archive_files", archive.clone()).await.text(), "2");
+    assert_eq!(post_text(&app, "/20/archive_files_size", archive.clone()).await.text(), "1005");
+    assert_eq!(post_text(&app, "/20/archive_files", archive[..600].to_vec()).await.status, StatusCode::BAD_REQUEST);
+    // cut on a block boundary: after the first entry, and between the two end blocks
+    assert_eq!(post_text(&app, "/20/archive_files", archive[..1024].to_vec()).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(post_text(&app, "/20/archive_files", archive[..3072].to_vec()).await.status, StatusCode::BAD_REQUEST);
+
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let first = commit_file(&repo, "santa.txt", "COOKIE", "Grinch", None);
+    let second = commit_file(&repo, "santa.txt", "no cookies left", "Santa", Some(first));
+    repo.branch("christmas", &repo.find_commit(second).unwrap(), false).unwrap();
+
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.append_dir_all(".", dir.path()).unwrap();
+    let repo_archive = builder.into_inner().unwrap();
+
+    let cookie = post_text(&app, "/20/cookie", repo_archive).await;
+    assert_eq!(cookie.text(), format!("Grinch {}", first));
+}
+
+#[tokio::test]
+async fn day_21_locates_s2_cells() {
+    let app = test_app().await;
+
+    let coords = get_uri(&app, "/21/coords/0100111110010011000110011001010101011111000010100011110001011011").await;
+    assert_eq!(coords.text(), "83°39'54.324''N 30°37'40.584''W");
+
+    let country = get_uri(&app, "/21/country/0010000111110000011111100000111010111100000100111101111011000101").await;
+    assert_eq!(country.text(), "Madagascar");
+
+    assert_eq!(get_uri(&app, "/21/coords/2").await.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn day_22_finds_presents_and_routes_rockets() {
+    let app = test_app().await;
+
+    let presents = post_text(&app, "/22/integers", "888\n77\n888\n22\n77\n").await;
+    assert_eq!(presents.text(), "🎁".repeat(22));
+
+    // zero is a valid count, negative numbers are not
+    assert_eq!(post_text(&app, "/22/integers", "0\n3\n0\n").await.text(), "🎁".repeat(3));
+    let negative = post_text(&app, "/22/integers", "3\n-1\n").await;
+    assert_eq!(negative.status, StatusCode::BAD_REQUEST);
+    assert_eq!(negative.json(), json!({"error": "line 2: \"-1\" is not a non-negative integer"}));
+
+    let route = post_text(&app, "/22/rocket", "5
+0 1 0
+-2 2 3
+3 -3 -5
+1 1 5
+4 3 5
+4
+0 1
+2 4
+3 4
+1 2
+").await;
+    assert_eq!(route.text(), "3 26.123");
+
+    let oversized = post_text(&app, "/22/rocket", "100000000000000\n0 0 0\n").await;
+    assert_eq!(oversized.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn disabled_days_are_not_routed() {
+    let mut disabled = config(&mock_pokeapi().await);
+    disabled.disabled_days = vec![22];
+    let app = app(unreachable_pool(), disabled);
+
+    assert_eq!(post_text(&app, "/22/integers", "1\n").await.status, StatusCode::NOT_FOUND);
+    assert_eq!(get_uri(&app, "/1/4/8").await.text(), "1728");
+}
+
+#[tokio::test]
+async fn metrics_count_requests_by_route() {
+    let app = test_app().await;
+
+    get_uri(&app, "/1/4/8").await;
+    get_uri(&app, "/8/weight/25").await;
+    get_uri(&app, "/-1/error").await;
+
+    let metrics = get_uri(&app, "/metrics").await;
+    assert_eq!(metrics.status, StatusCode::OK);
+    assert_eq!(metrics.headers["content-type"], "text/plain; version=0.0.4");
+
+    let text = metrics.text();
+    assert!(text.contains(r#"http_requests_total{method="GET",route="/1/*nums",status="200"} 1"#));
+    assert!(text.contains(r#"http_request_errors_total{method="GET",route="/-1/error",status="500"} 1"#));
+    assert!(text.contains(r#"pokeapi_requests_total{outcome="ok"} 1"#));
+    assert!(text.contains("db_pool_max_connections"));
+}
+
+#[tokio::test]
+async fn health_reports_liveness_and_readiness() {
+    let app = test_app().await;
+
+    let live = get_uri(&app, "/healthz").await;
+    assert_eq!(live.status, StatusCode::OK);
+    assert_eq!(live.json(), json!({"status": "ok"}));
+
+    // the pool never connects, so only the asset check passes
+    let ready = get_uri(&app, "/readyz").await;
+    assert_eq!(ready.status, StatusCode::SERVICE_UNAVAILABLE);
+    let report = ready.json();
+    assert_eq!(report["status"], json!("not_ready"));
+    assert_eq!(report["checks"]["database"]["status"], json!("error"));
+    assert_eq!(report["checks"]["migrations"]["status"], json!("error"));
+    assert_eq!(report["checks"]["assets"], json!({"status": "ok"}));
+}
+
+#[tokio::test]
+async fn readiness_gives_up_on_a_hanging_database() {
+    let app = app(hanging_pool().await, config(&mock_pokeapi().await));
+
+    // both database checks time out together rather than one after the other
+    let started = Instant::now();
+    let ready = get_uri(&app, "/readyz").await;
+    assert!(started.elapsed() < Duration::from_millis(2500), "took {:?}", started.elapsed());
+
+    assert_eq!(ready.status, StatusCode::SERVICE_UNAVAILABLE);
+    let report = ready.json();
+    assert_eq!(report["checks"]["database"], json!({"status": "error", "error": "timed out after 1500 ms"}));
+    assert_eq!(report["checks"]["migrations"], json!({"status": "error", "error": "timed out after 1500 ms"}));
+    assert_eq!(report["checks"]["assets"], json!({"status": "ok"}));
+}
+
+#[tokio::test]
+async fn streamed_bodies_respect_the_body_limit() {
+    let mut limited = config(&mock_pokeapi().await);
+    limited.body_limit = 1024;
+    let app = app(unreachable_pool(), limited);
+
+    let lines = "1\n".repeat(1000);
+    assert_eq!(post_text(&app, "/22/integers", lines.clone()).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(post_text(&app, "/6/stats", lines).await.status, StatusCode::BAD_REQUEST);
+
+    let archive = tar_archive(&[("a.txt", b"hello")]);
+    assert_eq!(post_text(&app, "/20/archive_files", archive).await.status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(post_text(&app, "/22/integers", "7\n").await.text(), "🎁".repeat(7));
+}
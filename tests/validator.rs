@@ -0,0 +1,65 @@
+mod common;
+
+use std::collections::HashSet;
+
+use sebatustra_christmas::{
+    config::CHALLENGE_DAYS,
+    validator::{load_cases, parse_cases, run_case}
+};
+
+use common::{app, config, mock_pokeapi, serve, unreachable_pool};
+
+const CASES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/validation/cases.jsonl");
+
+// tests/postgres.rs runs these against a real database
+const DATABASE_DAYS: [i32; 2] = [13, 18];
+
+#[test]
+fn recorded_cases_cover_every_challenge_day() {
+    let cases = load_cases(CASES).unwrap();
+    let days: HashSet<i32> = cases.iter().map(|case| case.day).collect();
+
+    let missing: Vec<i32> = CHALLENGE_DAYS.into_iter().filter(|day| !days.contains(day)).collect();
+    assert!(missing.is_empty(), "no cases for days {:?}", missing);
+}
+
+#[tokio::test]
+async fn recorded_cases_pass_in_process() {
+    let address = serve(app(unreachable_pool(), config(&mock_pokeapi().await)));
+    let base_url = format!("http://{}", address);
+    let client = reqwest::Client::new();
+
+    for case in load_cases(CASES).unwrap().iter().filter(|case| !DATABASE_DAYS.contains(&case.day)) {
+        let failures = run_case(&client, &base_url, case).await;
+        assert!(failures.is_empty(), "day {} {}: {}", case.day, case.name, failures.join("\n"));
+    }
+}
+
+#[tokio::test]
+async fn reports_how_a_response_differs() {
+    let address = serve(app(unreachable_pool(), config(&mock_pokeapi().await)));
+    let base_url = format!("http://{}", address);
+    let client = reqwest::Client::new();
+
+    let cases = parse_cases(concat!(
+        r#"{"day": 1, "name": "wrong body", "path": "/1/4/8", "expect": {"status": 201, "body": "1729"}}"#, "\n",
+        r#"{"day": 6, "name": "wrong json", "method": "POST", "path": "/6", "body": "elf", "expect": {"json": {"elf": 2, "elf on a shelf": 0, "shelf with no elf on it": 0}}}"#, "\n",
+        r#"{"day": 11, "name": "wrong header", "path": "/11/assets/decoration.png", "expect": {"headers": {"content-type": "image/gif"}}}"#
+    )).unwrap();
+
+    let failures = run_case(&client, &base_url, &cases[0]).await;
+    assert_eq!(failures, vec![
+        "status 200 != expected 201".to_string(),
+        "body differs:\n- 1729\n+ 1728".to_string()
+    ]);
+
+    let failures = run_case(&client, &base_url, &cases[1]).await;
+    assert_eq!(failures, vec![
+        "json differs:\n  {\n-   \"elf\": 2,\n+   \"elf\": 1,\n    \"elf on a shelf\": 0,\n    \"shelf with no elf on it\": 0\n  }".to_string()
+    ]);
+
+    let failures = run_case(&client, &base_url, &cases[2]).await;
+    assert_eq!(failures, vec![
+        "header content-type: Some(\"image/png\") != expected \"image/gif\"".to_string()
+    ]);
+}
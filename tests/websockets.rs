@@ -0,0 +1,124 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use sebatustra_christmas::{build_router, build_state};
+use serde_json::json;
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::Message as ClientMessage,
+    MaybeTlsStream,
+    WebSocketStream
+};
+
+use common::{app, config, mock_pokeapi, serve, unreachable_pool};
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn connect() -> Client {
+    let addr = serve(app(unreachable_pool(), config(&mock_pokeapi().await)));
+
+    let (client, _) = connect_async(format!("ws://{}/19/ws/ping", addr)).await.unwrap();
+    client
+}
+
+async fn next_message(client: &mut Client) -> Option<ClientMessage> {
+    match timeout(Duration::from_millis(300), client.next()).await {
+        Ok(Some(Ok(msg))) => Some(msg),
+        _ => None
+    }
+}
+
+async fn connect_to_room(addr: SocketAddr, room: u32, user: &str) -> Client {
+    let url = format!("ws://{}/19/ws/room/{}/user/{}", addr, room, user);
+    let (client, _) = connect_async(url).await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn stays_silent_until_served() {
+    let mut client = connect().await;
+
+    client.send(ClientMessage::Text("ping".into())).await.unwrap();
+
+    assert_eq!(next_message(&mut client).await, None);
+}
+
+#[tokio::test]
+async fn answers_every_ping_after_serve() {
+    let mut client = connect().await;
+
+    client.send(ClientMessage::Text("serve".into())).await.unwrap();
+    assert_eq!(next_message(&mut client).await, None);
+
+    for _ in 0..3 {
+        client.send(ClientMessage::Text("ping".into())).await.unwrap();
+        assert_eq!(next_message(&mut client).await, Some(ClientMessage::Text("pong".into())));
+    }
+}
+
+#[tokio::test]
+async fn ignores_other_text_without_disconnecting() {
+    let mut client = connect().await;
+
+    client.send(ClientMessage::Text("hello".into())).await.unwrap();
+    client.send(ClientMessage::Text("serve".into())).await.unwrap();
+    client.send(ClientMessage::Text("pong".into())).await.unwrap();
+    assert_eq!(next_message(&mut client).await, None);
+
+    client.send(ClientMessage::Text("ping".into())).await.unwrap();
+    assert_eq!(next_message(&mut client).await, Some(ClientMessage::Text("pong".into())));
+}
+
+#[tokio::test]
+async fn replies_to_ping_frames() {
+    let mut client = connect().await;
+
+    client.send(ClientMessage::Ping(vec![1, 2, 3])).await.unwrap();
+
+    assert_eq!(next_message(&mut client).await, Some(ClientMessage::Pong(vec![1, 2, 3])));
+}
+
+#[tokio::test]
+async fn completes_close_handshake() {
+    let mut client = connect().await;
+
+    client.send(ClientMessage::Close(None)).await.unwrap();
+
+    assert!(matches!(next_message(&mut client).await, Some(ClientMessage::Close(_))));
+}
+
+#[tokio::test]
+async fn broadcasts_chat_messages_to_the_room() {
+    // nothing listens behind the pool, so persisting fails and is only logged;
+    // the state is kept to check the rooms are cleaned up afterwards
+    let state = build_state(unreachable_pool(), config(&mock_pokeapi().await)).unwrap();
+    let chat = state.chat.clone();
+    let addr = serve(build_router(state));
+
+    let mut rudolph = connect_to_room(addr, 1, "rudolph").await;
+    let mut dasher = connect_to_room(addr, 1, "dasher").await;
+    let mut vixen = connect_to_room(addr, 2, "vixen").await;
+    // the handshake completes before the upgraded socket joins its room
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let too_long = json!({"message": "a".repeat(129)}).to_string();
+    rudolph.send(ClientMessage::Text(too_long)).await.unwrap();
+    rudolph.send(ClientMessage::Text(json!({"message": "hello"}).to_string())).await.unwrap();
+
+    let expected = ClientMessage::Text(json!({"user": "rudolph", "message": "hello"}).to_string());
+    assert_eq!(next_message(&mut rudolph).await, Some(expected.clone()));
+    assert_eq!(next_message(&mut dasher).await, Some(expected));
+    assert_eq!(next_message(&mut vixen).await, None);
+
+    let views = reqwest::get(format!("http://{}/19/views", addr)).await.unwrap().text().await.unwrap();
+    assert_eq!(views, "2");
+
+    for client in [&mut rudolph, &mut dasher, &mut vixen] {
+        client.close(None).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(chat.rooms.lock().await.is_empty());
+}